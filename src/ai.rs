@@ -1,156 +1,629 @@
-use crate::grid::{self, Mark::*};
-use std::cmp::{max, min};
+use crate::book::OpeningBook;
+use crate::error::GameError;
+use crate::game::{Game, GameResult, Player};
+use crate::grid;
+use std::cmp::{max, min, Reverse};
+use std::collections::HashMap;
+use std::fmt;
 use std::io;
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+const MAX_TRANSPOSITIONS: usize = 1 << 20;
+
+/// Outcomes for the AI side. Positions at the depth limit count as draws
+/// scored by the evaluation of their lines, those that end in a draw as 0.
 #[derive(Ord, PartialOrd, Eq, PartialEq, Copy, Clone)]
 enum Outcome {
-    Loss = 0,
-    Draw = 1,
-    Win = 2,
+    Loss,
+    Draw(i64),
+    Win,
 }
 
-pub struct AlphaBetaPruning {
-    max_side: grid::Mark,
-    min_side: grid::Mark,
+#[derive(Copy, Clone)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
 }
 
-impl AlphaBetaPruning {
-    pub fn new(ai_side: grid::Mark) -> AlphaBetaPruning {
-        AlphaBetaPruning {
-            max_side: ai_side,
-            min_side: match ai_side {
-                Nought => Cross,
-                Cross => Nought,
-            },
+#[derive(Copy, Clone)]
+struct Transposition {
+    side_to_move: Player,
+    outcome: Outcome,
+    bound: Bound,
+    best_move: Option<Move>,
+    // how many more moves the search looked ahead, see remaining_depth
+    draft: usize,
+}
+
+#[derive(Default, Debug, Copy, Clone)]
+pub struct SearchStats {
+    pub nodes_visited: u64,
+    pub cutoffs: u64,
+    pub max_depth: usize,
+    pub transposition_hits: u64,
+    pub elapsed: Duration,
+    pub stopped: bool,
+    pub from_book: bool,
+}
+
+impl SearchStats {
+    pub fn nodes_per_second(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.nodes_visited as f64 / secs
+        } else {
+            0.0
         }
     }
 
-    pub fn get_ai_side(&self) -> grid::Mark {
-        self.max_side
+    fn visit(&mut self, depth: usize) {
+        self.nodes_visited += 1;
+        self.max_depth = max(self.max_depth, depth);
     }
+}
 
-    pub fn try_make_move(&self, grid: &mut grid::Grid) -> bool {
-        if grid::get_winner(grid).is_some() {
-            return false;
+impl fmt::Display for SearchStats {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "nodes: {}, cutoffs: {}, max depth: {}, tt hits: {}, time: {:.3}s, nodes/s: {:.0}",
+            self.nodes_visited,
+            self.cutoffs,
+            self.max_depth,
+            self.transposition_hits,
+            self.elapsed.as_secs_f64(),
+            self.nodes_per_second()
+        )?;
+        if self.stopped {
+            write!(formatter, " (stopped)")?;
+        }
+        if self.from_book {
+            write!(formatter, " (book move)")?;
         }
+        Ok(())
+    }
+}
 
-        let mut alpha = Outcome::Loss;
-        let beta = Outcome::Win;
+/// Shared flag that makes a running search return its best move so far.
+#[derive(Clone, Default)]
+pub struct StopToken(Arc<AtomicBool>);
 
-        let mut best_outcome = Outcome::Loss; // worst outcome
-        let mut best_move: Option<Move> = None;
-        let mut last_move: Option<Move> = None;
+impl StopToken {
+    pub fn stop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
 
-        for ind in 0..grid.get_size() {
-            let next_move = Move {
-                what: self.max_side,
-                ind,
-            };
-            if let Some(mover) = RevertingMoveMaker::from_move(grid, next_move) {
-                println!("\rchecking move alternative {}...", ind);
-                last_move = Some(mover.get_move());
-                let outcome = self.minimizing_side(mover.grid, alpha, beta, 1);
-                if outcome > best_outcome {
-                    best_outcome = outcome;
-                    best_move = Some(mover.get_move());
-                }
-                if best_outcome >= beta {
-                    break;
-                }
-                alpha = max(alpha, best_outcome);
-            }
-        }
+    pub fn is_stopped(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
 
-        println!("\r{:<width$}", "DONE", width = grid.get_size());
-        if let Some(bm) = best_move {
-            PersistentMoveMaker::from_move(grid, bm);
-            true
-        } else if let Some(lm) = last_move {
-            PersistentMoveMaker::from_move(grid, lm);
-            true
-        } else {
-            false
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Common interface of the AI players, for games played on a single grid
+/// unless another game type is given.
+pub trait Engine<G = Game>: Send {
+    fn get_ai_side(&self) -> Player;
+
+    /// Token that interrupts the search when stopped from another thread.
+    fn get_stop_token(&self) -> StopToken;
+
+    /// Makes a move for the AI side, returns statistics of the search
+    /// or GameOver if there was no move to make.
+    fn try_make_move(&mut self, game: &mut G) -> Result<SearchStats, GameError>;
+
+    /// Thinks on the opponent's time until the stop token is raised,
+    /// which the caller is responsible for resetting beforehand.
+    fn ponder(&mut self, _game: &G) {}
+}
+
+pub struct AlphaBetaPruning {
+    max_side: Player,
+    min_side: Player,
+    transpositions: HashMap<grid::Grid, Transposition>,
+    stats: SearchStats,
+    stop_token: StopToken,
+    verbose: bool,
+    book: Option<Arc<OpeningBook>>,
+    move_ordering: bool,
+    depth_limit: Option<usize>,
+    // of the grid searched, for the evaluation at the depth limit
+    lines: Vec<Vec<grid::Position>>,
+    killers: Vec<[Option<grid::Position>; 2]>,
+    history: [Vec<u64>; 2],
+}
+
+impl AlphaBetaPruning {
+    pub fn new(ai_side: Player) -> AlphaBetaPruning {
+        AlphaBetaPruning {
+            max_side: ai_side,
+            min_side: ai_side.opponent(),
+            transpositions: HashMap::new(),
+            stats: SearchStats::default(),
+            stop_token: StopToken::default(),
+            verbose: true,
+            book: None,
+            move_ordering: true,
+            depth_limit: None,
+            lines: Vec::new(),
+            killers: Vec::new(),
+            history: [Vec::new(), Vec::new()],
         }
     }
 
+    /// Consults the book before searching.
+    pub fn set_opening_book(&mut self, book: Arc<OpeningBook>) {
+        self.book = Some(book);
+    }
+
+    /// Whether to print the search progress.
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
+    /// Scores positions at the given depth by their lines instead of
+    /// searching on, for boards too large to solve. Only moves next to other
+    /// marks are considered then, unless there is gravity.
+    pub fn set_depth_limit(&mut self, depth_limit: Option<usize>) {
+        self.depth_limit = depth_limit;
+    }
+
+    /// Whether to try the most promising moves first. Only worth disabling
+    /// to measure how many nodes the ordering saves.
+    pub fn set_move_ordering(&mut self, move_ordering: bool) {
+        self.move_ordering = move_ordering;
+    }
+
     fn maximizing_side(
-        &self,
-        grid: &mut grid::Grid,
+        &mut self,
+        game: &mut Game,
         mut alpha: Outcome,
         beta: Outcome,
         depth: usize,
     ) -> Outcome {
-        if let Some(outcome) = self.check_finished(grid) {
+        self.stats.visit(depth);
+        if self.stop_token.is_stopped() {
+            return Outcome::Draw(0); // discarded by the root
+        }
+        if let Some(outcome) = self.check_finished(game) {
+            return outcome;
+        }
+        if self.depth_limit.is_some_and(|limit| depth >= limit) {
+            return Outcome::Draw(game.evaluate(self.max_side, &self.lines));
+        }
+        if let Some(outcome) =
+            self.probe_transposition(game.get_grid(), self.max_side, alpha, beta, depth)
+        {
             return outcome;
         }
 
+        let initial_alpha = alpha;
         let mut best_outcome = Outcome::Loss; // worst outcome
+        let mut best_move: Option<Move> = None;
 
-        for ind in 0..grid.get_size() {
-            let next_move = Move {
-                what: self.max_side,
-                ind,
-            };
-            if let Some(mover) = RevertingMoveMaker::from_move(grid, next_move) {
-                print_dots(depth, mover.grid.get_size());
-                let outcome = self.minimizing_side(mover.grid, alpha, beta, depth + 1);
-                best_outcome = max(best_outcome, outcome);
+        for next_move in self.ordered_moves(game, depth) {
+            if let Some(mover) = RevertingMoveMaker::from_move(game, next_move) {
+                if self.verbose {
+                    print_dots(depth, mover.game.get_grid().get_size());
+                }
+                let outcome = self.minimizing_side(mover.game, alpha, beta, depth + 1);
+                if best_move.is_none() || outcome > best_outcome {
+                    best_outcome = outcome;
+                    best_move = Some(next_move);
+                }
                 if best_outcome >= beta {
+                    self.stats.cutoffs += 1;
+                    let size = mover.game.get_grid().get_size();
+                    self.record_cutoff(self.max_side, next_move.pos, depth, size);
                     break;
                 }
                 alpha = max(alpha, best_outcome);
             }
         }
 
+        self.store_transposition(game, best_outcome, best_move, initial_alpha, beta, depth);
         best_outcome
     }
 
     fn minimizing_side(
-        &self,
-        grid: &mut grid::Grid,
+        &mut self,
+        game: &mut Game,
         alpha: Outcome,
         mut beta: Outcome,
         depth: usize,
     ) -> Outcome {
-        if let Some(outcome) = self.check_finished(grid) {
+        self.stats.visit(depth);
+        if self.stop_token.is_stopped() {
+            return Outcome::Draw(0); // discarded by the root
+        }
+        if let Some(outcome) = self.check_finished(game) {
+            return outcome;
+        }
+        if self.depth_limit.is_some_and(|limit| depth >= limit) {
+            return Outcome::Draw(game.evaluate(self.max_side, &self.lines));
+        }
+        if let Some(outcome) =
+            self.probe_transposition(game.get_grid(), self.min_side, alpha, beta, depth)
+        {
             return outcome;
         }
 
+        let initial_beta = beta;
         let mut best_outcome = Outcome::Win; // worst outcome
+        let mut best_move: Option<Move> = None;
 
-        for ind in 0..grid.get_size() {
-            let next_move = Move {
-                what: self.min_side,
-                ind,
-            };
-            if let Some(mover) = RevertingMoveMaker::from_move(grid, next_move) {
-                print_dots(depth, mover.grid.get_size());
-                let outcome = self.maximizing_side(mover.grid, alpha, beta, depth + 1);
-                best_outcome = min(best_outcome, outcome);
+        for next_move in self.ordered_moves(game, depth) {
+            if let Some(mover) = RevertingMoveMaker::from_move(game, next_move) {
+                if self.verbose {
+                    print_dots(depth, mover.game.get_grid().get_size());
+                }
+                let outcome = self.maximizing_side(mover.game, alpha, beta, depth + 1);
+                if best_move.is_none() || outcome < best_outcome {
+                    best_outcome = outcome;
+                    best_move = Some(next_move);
+                }
                 if best_outcome <= alpha {
+                    self.stats.cutoffs += 1;
+                    let size = mover.game.get_grid().get_size();
+                    self.record_cutoff(self.min_side, next_move.pos, depth, size);
                     break;
                 }
                 beta = min(beta, best_outcome);
             }
         }
 
+        self.store_transposition(game, best_outcome, best_move, alpha, initial_beta, depth);
         best_outcome
     }
 
-    fn check_finished(&self, grid: &grid::Grid) -> Option<Outcome> {
-        if let Some(winner) = grid::get_winner(grid) {
-            if winner == self.max_side {
-                Some(Outcome::Win)
+    /// Lists the legal moves, most promising first: wins, blocks of the
+    /// opponent's wins, killer moves of the depth, moves that caused many
+    /// cutoffs so far, then moves next to other marks and near the center.
+    fn ordered_moves(&self, game: &Game, depth: usize) -> Vec<Move> {
+        let grid = game.get_grid();
+        let mut moves: Vec<Move> = all_moves(game).collect();
+        // with gravity there are few moves, and those on the floor matter too
+        if self.depth_limit.is_some()
+            && !game.has_gravity()
+            && moves.iter().any(|m| grid.is_near_stone(m.pos))
+        {
+            moves.retain(|m| grid.is_near_stone(m.pos));
+        }
+        if !self.move_ordering {
+            return moves;
+        }
+        let side = game.get_side_to_move();
+        let killers = self.killers.get(depth);
+        let history = &self.history[self.history_slot(side)];
+        // a line the side completes may count for the other side, as under
+        // misère rules or for Chaos
+        let completing_wins = game.line_winner(side) == side;
+        // a line of one mark can't be blocked for good when both may place
+        // it, though Chaos spoils it for Order by placing the other mark
+        let blocks = if game.is_order_and_chaos() {
+            !completing_wins
+        } else {
+            !game.is_misere() && !game.is_wild()
+        };
+        let marks: Vec<grid::Mark> = game.placeable_marks().collect();
+        let blocked = |what: grid::Mark| {
+            if game.is_order_and_chaos() {
+                // the other of the crosses and noughts both sides place
+                marks
+                    .iter()
+                    .copied()
+                    .find(|&mark| mark != what)
+                    .unwrap_or(what)
             } else {
-                Some(Outcome::Loss)
+                game.get_mark(game.next_player(side))
             }
-        } else if grid.is_full() {
-            Some(Outcome::Draw)
+        };
+        let doubled_center = (
+            grid.get_width() - 1,
+            grid.get_height() - 1,
+            grid.get_depth() - 1,
+        );
+        moves.sort_by_cached_key(|&Move { what, pos }| {
+            // completing a line for the other side loses, so those go last
+            let tier = if grid.completes_line(pos, what) {
+                if completing_wins {
+                    4
+                } else {
+                    0
+                }
+            } else if blocks && grid.completes_line(pos, blocked(what)) {
+                3
+            } else if killers.is_some_and(|k| k.contains(&Some(pos))) {
+                2
+            } else {
+                1
+            };
+            let center_distance = (2 * pos.x()).abs_diff(doubled_center.0)
+                + (2 * pos.y()).abs_diff(doubled_center.1)
+                + (2 * pos.z()).abs_diff(doubled_center.2);
+            Reverse((
+                tier,
+                history.get(pos.ind()).copied().unwrap_or(0),
+                grid.is_near_stone(pos),
+                Reverse(center_distance),
+            ))
+        });
+        moves
+    }
+
+    /// The AI keeps its history in the first slot, the opponent in the
+    /// second.
+    fn history_slot(&self, side: Player) -> usize {
+        usize::from(side != self.max_side)
+    }
+
+    /// Remembers the move that refuted the position for the move ordering.
+    /// Cutoffs close to the root save more work and weigh more.
+    fn record_cutoff(&mut self, side: Player, pos: grid::Position, depth: usize, size: usize) {
+        let remaining = size.saturating_sub(depth) as u64;
+        if self.killers.len() <= depth {
+            self.killers.resize(depth + 1, [None; 2]);
+        }
+        let killers = &mut self.killers[depth];
+        if killers[0] != Some(pos) {
+            killers[1] = killers[0];
+            killers[0] = Some(pos);
+        }
+        let slot = self.history_slot(side);
+        let history = &mut self.history[slot];
+        if history.len() <= pos.ind() {
+            history.resize(pos.ind() + 1, 0);
+        }
+        history[pos.ind()] += remaining * remaining;
+    }
+
+    /// How many more moves a search at the given depth looks ahead, all of
+    /// them without a depth limit.
+    fn remaining_depth(&self, depth: usize) -> usize {
+        self.depth_limit
+            .map_or(usize::MAX, |limit| limit.saturating_sub(depth))
+    }
+
+    fn probe_transposition(
+        &mut self,
+        grid: &grid::Grid,
+        side_to_move: Player,
+        alpha: Outcome,
+        beta: Outcome,
+        depth: usize,
+    ) -> Option<Outcome> {
+        let entry = self.transpositions.get(grid)?;
+        // a shallower search may have missed what this one would find
+        if entry.side_to_move != side_to_move || entry.draft < self.remaining_depth(depth) {
+            return None;
+        }
+        let usable = match entry.bound {
+            Bound::Exact => true,
+            Bound::Lower => entry.outcome >= beta,
+            Bound::Upper => entry.outcome <= alpha,
+        };
+        if usable {
+            self.stats.transposition_hits += 1;
+            Some(entry.outcome)
         } else {
             None
         }
     }
+
+    fn store_transposition(
+        &mut self,
+        game: &Game,
+        outcome: Outcome,
+        best_move: Option<Move>,
+        alpha: Outcome,
+        beta: Outcome,
+        depth: usize,
+    ) {
+        if self.stop_token.is_stopped() {
+            return;
+        }
+        let bound = if outcome <= alpha {
+            Bound::Upper
+        } else if outcome >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        if self.transpositions.len() >= MAX_TRANSPOSITIONS {
+            self.transpositions.clear();
+        }
+        self.transpositions.insert(
+            game.get_grid().clone(),
+            Transposition {
+                side_to_move: game.get_side_to_move(),
+                outcome,
+                bound,
+                best_move,
+                draft: self.remaining_depth(depth),
+            },
+        );
+    }
+
+    /// Returns the AI move in the given grid if its outcome is already known
+    /// to the depth of a new search.
+    fn probe_decided_move(&self, grid: &grid::Grid) -> Option<Move> {
+        let entry = self.transpositions.get(grid)?;
+        if entry.draft < self.remaining_depth(0) {
+            return None;
+        }
+        let decided = match entry.bound {
+            Bound::Exact => true,
+            Bound::Lower => entry.outcome == Outcome::Win,
+            Bound::Upper => entry.outcome == Outcome::Loss,
+        };
+        if entry.side_to_move == self.max_side && decided {
+            entry.best_move
+        } else {
+            None
+        }
+    }
+
+    /// Scores a finished game by the side the result names, which under
+    /// misère rules or in Order and Chaos need not be whoever completed the
+    /// line or whose mark it is.
+    fn check_finished(&self, game: &Game) -> Option<Outcome> {
+        match game.get_result()? {
+            GameResult::Win(winner) if winner == self.max_side => Some(Outcome::Win),
+            GameResult::Win(_) => Some(Outcome::Loss),
+            GameResult::Draw => Some(Outcome::Draw(0)),
+        }
+    }
+}
+
+impl Engine for AlphaBetaPruning {
+    fn get_ai_side(&self) -> Player {
+        self.max_side
+    }
+
+    fn get_stop_token(&self) -> StopToken {
+        self.stop_token.clone()
+    }
+
+    /// Makes the best move for the AI side. If the stop token is raised
+    /// during the search, the best move found so far is made. Only games of
+    /// two players are searched, see the paranoid module for more.
+    fn try_make_move(&mut self, game: &mut Game) -> Result<SearchStats, GameError> {
+        if game.get_player_count() != 2 {
+            return Err(GameError::InvalidPlayers);
+        }
+        if game.is_over() {
+            return Err(GameError::GameOver);
+        }
+        if game.get_side_to_move() != self.max_side {
+            return Err(GameError::WrongSide(self.max_side));
+        }
+        self.min_side = game.next_player(self.max_side);
+        self.lines = game.get_grid().lines();
+
+        let start = Instant::now();
+        self.stop_token.reset();
+        self.stats = SearchStats::default();
+        self.stats.visit(0);
+
+        // the book is built for the standard rules
+        let book_move = self
+            .book
+            .as_ref()
+            .filter(|_| game.has_standard_rules())
+            .and_then(|book| book.lookup(game.get_grid()));
+        let playable = |pos| game.get_grid().is_empty_cell(pos) && !game.is_forbidden(pos);
+        if let Some(pos) = book_move.filter(|&pos| playable(pos)) {
+            self.stats.from_book = true;
+            self.stats.elapsed = start.elapsed();
+            PersistentMoveMaker::from_move(
+                game,
+                Move {
+                    what: game.get_mark(self.max_side),
+                    pos,
+                },
+            );
+            return Ok(self.stats);
+        }
+
+        if let Some(decided) = self.probe_decided_move(game.get_grid()) {
+            self.stats.transposition_hits += 1;
+            self.stats.elapsed = start.elapsed();
+            PersistentMoveMaker::from_move(game, decided);
+            return Ok(self.stats);
+        }
+
+        let mut alpha = Outcome::Loss;
+        let beta = Outcome::Win;
+
+        let mut best_outcome = Outcome::Loss; // worst outcome
+        let mut best_move: Option<Move> = None;
+        let mut last_move: Option<Move> = None;
+
+        self.killers.clear();
+        for next_move in self.ordered_moves(game, 0) {
+            if let Some(mover) = RevertingMoveMaker::from_move(game, next_move) {
+                if self.verbose {
+                    println!("\rchecking move alternative {}...", next_move.pos.ind());
+                }
+                last_move = Some(mover.get_move());
+                let outcome = self.minimizing_side(mover.game, alpha, beta, 1);
+                if self.stop_token.is_stopped() {
+                    self.stats.stopped = true;
+                    break;
+                }
+                if outcome > best_outcome {
+                    best_outcome = outcome;
+                    best_move = Some(mover.get_move());
+                }
+                if best_outcome >= beta {
+                    self.stats.cutoffs += 1;
+                    break;
+                }
+                alpha = max(alpha, best_outcome);
+            }
+        }
+
+        if self.verbose {
+            println!("\r{:<width$}", "DONE", width = game.get_grid().get_size());
+        }
+        self.stats.elapsed = start.elapsed();
+        if let Some(bm) = best_move {
+            PersistentMoveMaker::from_move(game, bm);
+            Ok(self.stats)
+        } else if let Some(lm) = last_move {
+            PersistentMoveMaker::from_move(game, lm);
+            Ok(self.stats)
+        } else {
+            Err(GameError::GameOver)
+        }
+    }
+
+    /// Searches the replies the opponent may make in the given game, so that
+    /// the following call to try_make_move can reuse the results. Runs until
+    /// the search is complete or the stop token is raised, which the caller
+    /// is responsible for resetting beforehand.
+    fn ponder(&mut self, game: &Game) {
+        if game.is_over()
+            || game.get_player_count() != 2
+            || game.get_side_to_move() == self.max_side
+        {
+            return;
+        }
+        self.min_side = game.get_side_to_move();
+        self.lines = game.get_grid().lines();
+        let mut game = game.clone();
+        let verbose = self.verbose;
+        self.verbose = false;
+
+        let predicted = self
+            .transpositions
+            .get(game.get_grid())
+            .filter(|entry| entry.side_to_move == self.min_side)
+            .and_then(|entry| entry.best_move);
+        let replies: Vec<Move> = predicted
+            .into_iter()
+            .chain(all_moves(&game).filter(|&m| Some(m) != predicted))
+            .collect();
+
+        for reply in replies {
+            // the position after the reply is the root of the next search,
+            // which has to look as far ahead to reuse the results
+            if let Some(mover) = RevertingMoveMaker::from_move(&mut game, reply) {
+                self.maximizing_side(mover.game, Outcome::Loss, Outcome::Win, 0);
+            }
+            if self.stop_token.is_stopped() {
+                break;
+            }
+        }
+
+        self.verbose = verbose;
+    }
 }
 
 fn print_dots(count: usize, line_width: usize) {
@@ -162,28 +635,34 @@ fn print_dots(count: usize, line_width: usize) {
     io::stdout().flush().unwrap();
 }
 
+/// Every mark the side to move may place at every legal position.
+fn all_moves(game: &Game) -> impl Iterator<Item = Move> + '_ {
+    game.legal_moves()
+        .flat_map(move |pos| game.placeable_marks().map(move |what| Move { what, pos }))
+}
+
 #[derive(Copy, Clone, Eq, PartialEq)]
 struct Move {
     what: grid::Mark,
-    ind: usize,
+    pos: grid::Position,
 }
 
 trait MoveMaker<'a>: Sized {
-    fn from_move(grid: &'a mut grid::Grid, m: Move) -> Option<Self>;
+    fn from_move(game: &'a mut Game, m: Move) -> Option<Self>;
     fn get_move(&self) -> Move;
 }
 
 /// reverting move maker!
 struct RevertingMoveMaker<'a> {
-    grid: &'a mut grid::Grid,
+    game: &'a mut Game,
     saved_move: Move,
 }
 
 impl<'a> MoveMaker<'a> for RevertingMoveMaker<'a> {
-    fn from_move(grid: &mut grid::Grid, m: Move) -> Option<RevertingMoveMaker> {
-        match grid.set_at_ind(m.ind, m.what) {
+    fn from_move(game: &mut Game, m: Move) -> Option<RevertingMoveMaker<'_>> {
+        match game.make_move(m.what, m.pos) {
             Ok(_) => Some(RevertingMoveMaker {
-                grid,
+                game,
                 saved_move: m,
             }),
             Err(_) => None,
@@ -197,7 +676,7 @@ impl<'a> MoveMaker<'a> for RevertingMoveMaker<'a> {
 
 impl Drop for RevertingMoveMaker<'_> {
     fn drop(&mut self) {
-        self.grid.unset_at_ind(self.saved_move.ind);
+        self.game.undo_move();
     }
 }
 
@@ -207,8 +686,8 @@ struct PersistentMoveMaker {
 }
 
 impl MoveMaker<'_> for PersistentMoveMaker {
-    fn from_move(grid: &mut grid::Grid, m: Move) -> Option<PersistentMoveMaker> {
-        match grid.set_at_ind(m.ind, m.what) {
+    fn from_move(game: &mut Game, m: Move) -> Option<PersistentMoveMaker> {
+        match game.make_move(m.what, m.pos) {
             Ok(_) => Some(PersistentMoveMaker { saved_move: m }),
             Err(_) => None,
         }
@@ -222,41 +701,34 @@ impl MoveMaker<'_> for PersistentMoveMaker {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::grid::Mark::*;
 
     #[test]
     fn test_reverting_move_maker() {
-        let mut g = grid::Grid::new(3);
+        let mut game = Game::new(3);
+        let pos = game.get_grid().position(1, 1).unwrap();
         {
-            let opt_mm = RevertingMoveMaker::from_move(
-                &mut g,
-                Move {
-                    what: Nought,
-                    ind: 4,
-                },
-            );
+            let opt_mm = RevertingMoveMaker::from_move(&mut game, Move { what: Cross, pos });
 
             let mm = opt_mm.unwrap();
-            assert_eq!(Some(Nought), mm.grid.get_at_pos(1, 1));
+            assert_eq!(Ok(Some(Cross)), mm.game.get_grid().get_at_pos(1, 1));
         }
-        assert!(g.get_at_pos(1, 1).is_none());
+        assert_eq!(Ok(None), game.get_grid().get_at_pos(1, 1));
+        assert_eq!(Player::FIRST, game.get_side_to_move());
     }
 
     #[test]
     fn test_persistent_move_maker() {
-        let mut g = grid::Grid::new(3);
+        let mut game = Game::new(3);
+        let pos = game.get_grid().position(1, 1).unwrap();
         {
-            let opt_mm = PersistentMoveMaker::from_move(
-                &mut g,
-                Move {
-                    what: Nought,
-                    ind: 4,
-                },
-            );
+            let opt_mm = PersistentMoveMaker::from_move(&mut game, Move { what: Cross, pos });
 
             assert!(opt_mm.is_some());
-            assert_eq!(Some(Nought), g.get_at_pos(1, 1));
+            assert_eq!(Ok(Some(Cross)), game.get_grid().get_at_pos(1, 1));
         }
-        assert_eq!(Some(Nought), g.get_at_pos(1, 1));
+        assert_eq!(Ok(Some(Cross)), game.get_grid().get_at_pos(1, 1));
+        assert_eq!(Player::SECOND, game.get_side_to_move());
     }
 
     #[test]
@@ -268,19 +740,21 @@ mod tests {
         g.set_at_pos(1, 1, Cross).unwrap();
         g.set_at_pos(1, 2, Nought).unwrap();
 
-        let engine = AlphaBetaPruning::new(Cross);
-        engine.try_make_move(&mut g);
+        let mut game = Game::from_grid(g).unwrap();
+        let mut engine = AlphaBetaPruning::new(Player::FIRST);
+        engine.try_make_move(&mut game).unwrap();
+        let g = game.get_grid();
 
         for x in 0..g.get_side_length() {
             for y in 0..g.get_side_length() {
                 match (x, y) {
                     (2, 0) | (1, 1) | (0, 2) => {
-                        assert_eq!(Some(Cross), g.get_at_pos(x, y), "x={} y={}", x, y)
+                        assert_eq!(Ok(Some(Cross)), g.get_at_pos(x, y), "x={} y={}", x, y)
                     }
                     (2, 2) | (1, 2) => {
-                        assert_eq!(Some(Nought), g.get_at_pos(x, y), "x={} y={}", x, y)
+                        assert_eq!(Ok(Some(Nought)), g.get_at_pos(x, y), "x={} y={}", x, y)
                     }
-                    _ => assert!(g.get_at_pos(x, y).is_none(), "x={} y={}", x, y),
+                    _ => assert_eq!(Ok(None), g.get_at_pos(x, y), "x={} y={}", x, y),
                 }
             }
         }
@@ -296,19 +770,21 @@ mod tests {
         g.set_at_pos(0, 2, Nought).unwrap();
         g.set_at_pos(2, 1, Cross).unwrap();
 
-        let engine = AlphaBetaPruning::new(Nought);
-        engine.try_make_move(&mut g);
+        let mut game = Game::from_grid(g).unwrap();
+        let mut engine = AlphaBetaPruning::new(Player::SECOND);
+        engine.try_make_move(&mut game).unwrap();
+        let g = game.get_grid();
 
         for x in 0..g.get_side_length() {
             for y in 0..g.get_side_length() {
                 match (x, y) {
                     (2, 0) | (1, 1) | (2, 1) => {
-                        assert_eq!(Some(Cross), g.get_at_pos(x, y), "x={} y={}", x, y)
+                        assert_eq!(Ok(Some(Cross)), g.get_at_pos(x, y), "x={} y={}", x, y)
                     }
                     (0, 0) | (0, 2) | (0, 1) => {
-                        assert_eq!(Some(Nought), g.get_at_pos(x, y), "x={} y={}", x, y)
+                        assert_eq!(Ok(Some(Nought)), g.get_at_pos(x, y), "x={} y={}", x, y)
                     }
-                    _ => assert!(g.get_at_pos(x, y).is_none(), "x={} y={}", x, y),
+                    _ => assert_eq!(Ok(None), g.get_at_pos(x, y), "x={} y={}", x, y),
                 }
             }
         }
@@ -323,19 +799,21 @@ mod tests {
         g.set_at_pos(0, 0, Cross).unwrap();
         g.set_at_pos(2, 2, Nought).unwrap();
 
-        let engine = AlphaBetaPruning::new(Cross);
-        engine.try_make_move(&mut g);
+        let mut game = Game::from_grid(g).unwrap();
+        let mut engine = AlphaBetaPruning::new(Player::FIRST);
+        engine.try_make_move(&mut game).unwrap();
+        let g = game.get_grid();
 
         for x in 0..g.get_side_length() {
             for y in 0..g.get_side_length() {
                 match (x, y) {
                     (0, 0) | (1, 1) | (1, 2) => {
-                        assert_eq!(Some(Cross), g.get_at_pos(x, y), "x={} y={}", x, y)
+                        assert_eq!(Ok(Some(Cross)), g.get_at_pos(x, y), "x={} y={}", x, y)
                     }
                     (0, 2) | (2, 2) => {
-                        assert_eq!(Some(Nought), g.get_at_pos(x, y), "x={} y={}", x, y)
+                        assert_eq!(Ok(Some(Nought)), g.get_at_pos(x, y), "x={} y={}", x, y)
                     }
-                    _ => assert!(g.get_at_pos(x, y).is_none(), "x={} y={}", x, y),
+                    _ => assert_eq!(Ok(None), g.get_at_pos(x, y), "x={} y={}", x, y),
                 }
             }
         }
@@ -349,41 +827,170 @@ mod tests {
         g.set_at_pos(0, 0, Nought).unwrap();
         g.set_at_pos(2, 0, Cross).unwrap();
 
-        let engine = AlphaBetaPruning::new(Nought);
-        engine.try_make_move(&mut g);
+        let mut game = Game::from_grid(g).unwrap();
+        let mut engine = AlphaBetaPruning::new(Player::SECOND);
+        engine.try_make_move(&mut game).unwrap();
+        let g = game.get_grid();
 
         for x in 0..g.get_side_length() {
             for y in 0..g.get_side_length() {
                 match (x, y) {
                     (2, 0) | (1, 1) => {
-                        assert_eq!(Some(Cross), g.get_at_pos(x, y), "x={} y={}", x, y)
+                        assert_eq!(Ok(Some(Cross)), g.get_at_pos(x, y), "x={} y={}", x, y)
                     }
                     (0, 0) | (0, 2) => {
-                        assert_eq!(Some(Nought), g.get_at_pos(x, y), "x={} y={}", x, y)
+                        assert_eq!(Ok(Some(Nought)), g.get_at_pos(x, y), "x={} y={}", x, y)
                     }
-                    _ => assert!(g.get_at_pos(x, y).is_none(), "x={} y={}", x, y),
+                    _ => assert_eq!(Ok(None), g.get_at_pos(x, y), "x={} y={}", x, y),
                 }
             }
         }
     }
 
+    #[test]
+    fn test_ai_averts_defeat_with_other_marks() {
+        let mut g = grid::Grid::new(3);
+
+        g.set_at_pos(1, 1, Square).unwrap();
+        g.set_at_pos(0, 0, Triangle).unwrap();
+        g.set_at_pos(2, 0, Square).unwrap();
+
+        let mut game = Game::with_players(g, &[Square, Triangle]).unwrap();
+        let mut engine = AlphaBetaPruning::new(Player::SECOND);
+        engine.try_make_move(&mut game).unwrap();
+        assert_eq!(Ok(Some(Triangle)), game.get_grid().get_at_pos(0, 2));
+
+        game.make_move(Square, game.get_grid().position(0, 1).unwrap())
+            .unwrap();
+        engine.try_make_move(&mut game).unwrap();
+        assert_eq!(Ok(Some(Triangle)), game.get_grid().get_at_pos(2, 1));
+    }
+
     #[test]
     fn test_ai_tolerates_full_grid() {
         let mut g = grid::Grid::new(3);
 
-        for x in 0..g.get_side_length() {
-            for y in 0..g.get_side_length() {
-                g.set_at_pos(x, y, Cross).unwrap();
-            }
+        for (ind, c) in "XOXXOOOXX".chars().enumerate() {
+            g.set_at_ind(ind, if c == 'X' { Cross } else { Nought })
+                .unwrap();
         }
+        let mut game = Game::from_grid(g.clone()).unwrap();
+
+        let mut engine = AlphaBetaPruning::new(Player::SECOND);
+        assert_eq!(
+            GameError::GameOver,
+            engine.try_make_move(&mut game).unwrap_err()
+        );
+        assert_eq!(&g, game.get_grid());
+    }
 
-        let engine = AlphaBetaPruning::new(Nought);
-        engine.try_make_move(&mut g);
+    #[test]
+    fn test_ai_waits_for_its_turn() {
+        let mut game = Game::new(3);
+
+        let mut engine = AlphaBetaPruning::new(Player::SECOND);
+        assert_eq!(
+            GameError::WrongSide(Player::SECOND),
+            engine.try_make_move(&mut game).unwrap_err()
+        );
+        assert_eq!(0, game.get_move_count());
+    }
 
-        for x in 0..g.get_side_length() {
-            for y in 0..g.get_side_length() {
-                assert_eq!(Some(Cross), g.get_at_pos(x, y));
-            }
-        }
+    #[test]
+    fn test_search_reports_stats() {
+        let mut game = Game::new(3);
+
+        let mut engine = AlphaBetaPruning::new(Player::FIRST);
+        let stats = engine.try_make_move(&mut game).unwrap();
+
+        assert!(stats.nodes_visited > 9);
+        assert!(stats.cutoffs > 0);
+        assert!(stats.transposition_hits > 0);
+        assert_eq!(9, stats.max_depth);
+    }
+
+    #[test]
+    fn test_stopped_search_still_makes_move() {
+        let mut game = Game::new(4);
+
+        let mut engine = AlphaBetaPruning::new(Player::FIRST);
+        let token = engine.get_stop_token();
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            token.stop();
+        });
+        let stats = engine.try_make_move(&mut game).unwrap();
+        stopper.join().unwrap();
+
+        assert!(stats.stopped);
+        assert_eq!(1, game.get_move_count());
+    }
+
+    #[test]
+    fn test_pondering_answers_reply_from_table() {
+        let mut game = Game::new(3);
+        let center = game.get_grid().position(1, 1).unwrap();
+        game.make_move(Cross, center).unwrap();
+
+        let mut engine = AlphaBetaPruning::new(Player::FIRST);
+        engine.ponder(&game);
+        let corner = game.get_grid().position(0, 0).unwrap();
+        game.make_move(Nought, corner).unwrap();
+        let stats = engine.try_make_move(&mut game).unwrap();
+
+        assert_eq!(1, stats.nodes_visited);
+        assert_eq!(1, stats.transposition_hits);
+        assert_eq!(3, game.get_move_count());
+    }
+
+    #[test]
+    fn test_limited_pondering_answers_reply_from_table() {
+        let mut game = Game::new(4);
+        let corner = game.get_grid().position(0, 0).unwrap();
+        game.make_move(Cross, corner).unwrap();
+
+        let mut engine = AlphaBetaPruning::new(Player::FIRST);
+        engine.set_verbose(false);
+        engine.set_depth_limit(Some(3));
+        engine.ponder(&game);
+        let center = game.get_grid().position(1, 1).unwrap();
+        game.make_move(Nought, center).unwrap();
+        let stats = engine.try_make_move(&mut game).unwrap();
+
+        assert_eq!(1, stats.nodes_visited);
+        assert_eq!(1, stats.transposition_hits);
+        assert_eq!(3, game.get_move_count());
+    }
+
+    #[test]
+    fn test_ai_plays_book_move() {
+        let mut game = Game::new(3);
+        let mut book = OpeningBook::default();
+        book.insert(game.get_grid(), game.get_grid().position_of_ind(1).unwrap());
+
+        let mut engine = AlphaBetaPruning::new(Player::FIRST);
+        engine.set_opening_book(Arc::new(book));
+        let stats = engine.try_make_move(&mut game).unwrap();
+
+        assert!(stats.from_book);
+        assert_eq!(Ok(Some(Cross)), game.get_grid().get_at_ind(1));
+    }
+
+    #[test]
+    fn test_search_reports_nothing_when_finished() {
+        let mut g = grid::Grid::new(3);
+        g.set_at_pos(1, 0, Cross).unwrap();
+        g.set_at_pos(0, 0, Nought).unwrap();
+        g.set_at_pos(2, 0, Cross).unwrap();
+        g.set_at_pos(1, 1, Nought).unwrap();
+        g.set_at_pos(0, 1, Cross).unwrap();
+        g.set_at_pos(2, 2, Nought).unwrap();
+        let mut game = Game::from_grid(g).unwrap();
+
+        let mut engine = AlphaBetaPruning::new(Player::FIRST);
+        assert_eq!(
+            GameError::GameOver,
+            engine.try_make_move(&mut game).unwrap_err()
+        );
     }
 }
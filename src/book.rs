@@ -0,0 +1,275 @@
+use crate::ai::{self, Engine};
+use crate::game::Game;
+use crate::grid::{Grid, Mark, Position};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Maps canonical positions to the best known move in them. Positions that
+/// are rotations or reflections of each other share one entry.
+///
+/// The file format is one entry per line: the grid cells row by row
+/// ('X', 'O' or '.' for empty) followed by the index of the move in that
+/// grid. Empty lines and lines starting with '#' are ignored.
+#[derive(Default, Debug)]
+pub struct OpeningBook {
+    entries: HashMap<String, usize>,
+}
+
+impl OpeningBook {
+    pub fn load(path: &Path) -> io::Result<OpeningBook> {
+        let mut book = OpeningBook::default();
+        for (line_no, line) in fs::read_to_string(path)?.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid opening book entry on line {}", line_no + 1),
+                )
+            };
+            let mut fields = line.split_whitespace();
+            let (cells, ind) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(cells), Some(ind), None) => (cells, ind),
+                _ => return Err(invalid()),
+            };
+            let side_length = (cells.len() as f64).sqrt() as usize;
+            let ind = ind.parse::<usize>().map_err(|_| invalid())?;
+            if side_length * side_length != cells.len()
+                || !cells.chars().all(|c| "XO.".contains(c))
+                || cells.as_bytes().get(ind) != Some(&b'.')
+            {
+                return Err(invalid());
+            }
+            book.entries.insert(cells.to_string(), ind);
+        }
+        Ok(book)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut lines: Vec<String> = self
+            .entries
+            .iter()
+            .map(|(cells, ind)| format!("{} {}", cells, ind))
+            .collect();
+        lines.sort_by_key(|line| (line.len(), line.clone()));
+        let mut contents = String::from("# ttt opening book: <cells> <move index>\n");
+        for line in lines {
+            contents.push_str(&line);
+            contents.push('\n');
+        }
+        fs::write(path, contents)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the book move in the given grid, if any. Books only cover
+    /// plain grids.
+    pub fn lookup(&self, grid: &Grid) -> Option<Position> {
+        if !grid.is_plain() {
+            return None;
+        }
+        let (key, symmetry) = canonical_form(grid);
+        let canonical_ind = *self.entries.get(&key)?;
+        let side_length = grid.get_side_length();
+        grid.positions()
+            .find(|pos| transform(symmetry, side_length, pos.ind()) == canonical_ind)
+    }
+
+    pub fn insert(&mut self, grid: &Grid, pos: Position) {
+        let (key, symmetry) = canonical_form(grid);
+        let canonical_ind = transform(symmetry, grid.get_side_length(), pos.ind());
+        self.entries.insert(key, canonical_ind);
+    }
+}
+
+/// Builds a book for all positions up to the given number of plies, giving
+/// the engine the time budget to search each of them. Positions whose search
+/// doesn't finish in time are left out.
+pub fn generate(side_length: usize, plies: usize, budget: Duration) -> OpeningBook {
+    let mut book = OpeningBook::default();
+    let mut positions = vec![Game::new(side_length)];
+
+    for ply in 0..plies {
+        let mut next_positions = Vec::new();
+        let mut seen = HashSet::new();
+        for (count, position) in positions.iter().enumerate() {
+            if position.is_over() {
+                continue;
+            }
+            println!(
+                "Ply {}: searching position {} of {}",
+                ply + 1,
+                count + 1,
+                positions.len()
+            );
+            if let Some(pos) = search_with_budget(position, budget) {
+                book.insert(position.get_grid(), pos);
+            }
+            for pos in position.legal_moves() {
+                let mut next = position.clone();
+                next.make_move(position.get_mark_to_move(), pos).unwrap();
+                if seen.insert(canonical_form(next.get_grid()).0) {
+                    next_positions.push(next);
+                }
+            }
+        }
+        positions = next_positions;
+    }
+    book
+}
+
+fn search_with_budget(position: &Game, budget: Duration) -> Option<Position> {
+    let mut engine = ai::AlphaBetaPruning::new(position.get_side_to_move());
+    engine.set_verbose(false);
+    let stop_token = engine.get_stop_token();
+    let (search_done, timeout) = mpsc::channel::<()>();
+    let timer = thread::spawn(move || {
+        if let Err(mpsc::RecvTimeoutError::Timeout) = timeout.recv_timeout(budget) {
+            stop_token.stop();
+        }
+    });
+
+    let mut game = position.clone();
+    let result = engine.try_make_move(&mut game);
+    drop(search_done);
+    timer.join().unwrap();
+
+    // an interrupted search only guesses from the moves it got through
+    match result {
+        Ok(stats) if !stats.stopped => game.undo_move(),
+        _ => None,
+    }
+}
+
+/// Maps a cell index through one of the 8 symmetries of the square.
+fn transform(symmetry: usize, side_length: usize, ind: usize) -> usize {
+    let last = side_length - 1;
+    let (x, y) = (ind % side_length, ind / side_length);
+    let (x, y) = match symmetry {
+        0 => (x, y),
+        1 => (last - y, x),
+        2 => (last - x, last - y),
+        3 => (y, last - x),
+        4 => (last - x, y),
+        5 => (x, last - y),
+        6 => (y, x),
+        _ => (last - y, last - x),
+    };
+    y * side_length + x
+}
+
+/// Returns the smallest textual form of the grid among all its symmetries,
+/// together with the symmetry that produces it.
+pub fn canonical_form(grid: &Grid) -> (String, usize) {
+    let side_length = grid.get_side_length();
+    (0..8)
+        .map(|symmetry| {
+            let mut cells = vec!['.'; grid.get_size()];
+            for pos in grid.positions() {
+                cells[transform(symmetry, side_length, pos.ind())] =
+                    grid.get(pos).map_or('.', Mark::symbol);
+            }
+            (cells.into_iter().collect(), symmetry)
+        })
+        .min()
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::WinCondition;
+
+    #[test]
+    fn test_symmetric_positions_share_entry() {
+        let mut book = OpeningBook::default();
+        let mut g = Grid::new(3);
+        g.set_at_pos(0, 0, Mark::Cross).unwrap();
+        book.insert(&g, g.position(1, 1).unwrap());
+
+        let mut mirrored = Grid::new(3);
+        mirrored.set_at_pos(2, 2, Mark::Cross).unwrap();
+        assert_eq!(mirrored.position(1, 1).ok(), book.lookup(&mirrored));
+        assert_eq!(1, book.len());
+    }
+
+    #[test]
+    fn test_lookup_transforms_move_back() {
+        let mut book = OpeningBook::default();
+        let mut g = Grid::new(4);
+        g.set_at_pos(0, 0, Mark::Cross).unwrap();
+        book.insert(&g, g.position(1, 0).unwrap());
+
+        let mut rotated = Grid::new(4);
+        rotated.set_at_pos(3, 0, Mark::Cross).unwrap();
+        let pos = book.lookup(&rotated).unwrap();
+        assert!([(2, 0), (3, 1)].contains(&(pos.x(), pos.y())), "{:?}", pos);
+    }
+
+    #[test]
+    fn test_lookup_skips_other_shapes() {
+        let mut book = OpeningBook::default();
+        book.insert(&Grid::new(3), Grid::new(3).position(1, 1).unwrap());
+        let wide = Grid::rectangular(3, 5, WinCondition::full_line(3)).unwrap();
+        assert_eq!(None, book.lookup(&wide));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut book = OpeningBook::default();
+        let mut g = Grid::new(3);
+        book.insert(&g, g.position(1, 1).unwrap());
+        g.set_at_pos(1, 1, Mark::Cross).unwrap();
+        book.insert(&g, g.position(0, 0).unwrap());
+
+        let path = std::env::temp_dir().join("ttt_test_save_and_load.book");
+        book.save(&path).unwrap();
+        let loaded = OpeningBook::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(2, loaded.len());
+        assert_eq!(Some(4), loaded.lookup(&Grid::new(3)).map(|pos| pos.ind()));
+        assert!(loaded.lookup(&g).is_some());
+    }
+
+    #[test]
+    fn test_load_rejects_occupied_move() {
+        let path = std::env::temp_dir().join("ttt_test_load_rejects_occupied_move.book");
+        fs::write(&path, "# comment\n\nX........ 0\n").unwrap();
+        let result = OpeningBook::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+    }
+
+    #[test]
+    fn test_generate_covers_replies() {
+        let book = generate(3, 2, Duration::from_secs(10));
+
+        // the empty grid plus three distinct replies: corner, edge and center
+        assert_eq!(4, book.len());
+        let mut g = Grid::new(3);
+        g.set_at_pos(1, 1, Mark::Cross).unwrap();
+        let reply = book.lookup(&g).unwrap();
+        assert!([0, 2, 6, 8].contains(&reply.ind()), "{:?}", reply);
+    }
+
+    #[test]
+    fn test_generate_skips_unfinished_searches() {
+        let book = generate(4, 1, Duration::from_millis(50));
+        assert!(book.is_empty());
+    }
+}
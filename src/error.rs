@@ -0,0 +1,59 @@
+use crate::game::Player;
+use crate::grid::Mark;
+use std::error::Error;
+use std::fmt;
+
+/// Moves and settings that break the rules of the game.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum GameError {
+    OccupiedCell(Mark),
+    OutOfBounds,
+    InvalidSize(usize),
+    GameOver,
+    WrongSide(Player),
+    WrongMark(Mark),
+    ForbiddenMove,
+    ColumnFull,
+    InactiveBoard,
+    BlockedCell,
+    TooManyObstacles(usize),
+    InvalidPlayers,
+    CollapsePending,
+    UnsupportedRules,
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::OccupiedCell(mark) => {
+                write!(formatter, "Square already contains {:?}", mark)
+            }
+            GameError::OutOfBounds => write!(formatter, "Square is outside the grid"),
+            GameError::InvalidSize(size) => write!(formatter, "Invalid grid size {}", size),
+            GameError::GameOver => write!(formatter, "The game is already over"),
+            GameError::WrongSide(player) => write!(formatter, "It's not {}'s turn", player),
+            GameError::WrongMark(mark) => {
+                write!(formatter, "{:?} is not a mark of the side to move", mark)
+            }
+            GameError::ForbiddenMove => write!(formatter, "The move is forbidden by the rules"),
+            GameError::ColumnFull => write!(formatter, "The column is full"),
+            GameError::InactiveBoard => write!(formatter, "The move must be on an active board"),
+            GameError::BlockedCell => write!(formatter, "Square is blocked"),
+            GameError::TooManyObstacles(count) => {
+                write!(formatter, "Not enough empty squares to block {}", count)
+            }
+            GameError::CollapsePending => write!(formatter, "The cycle has to be collapsed first"),
+            GameError::UnsupportedRules => {
+                write!(formatter, "Only the standard rules are supported")
+            }
+            GameError::InvalidPlayers => {
+                write!(
+                    formatter,
+                    "Each of two or more players needs their own mark"
+                )
+            }
+        }
+    }
+}
+
+impl Error for GameError {}
@@ -1,26 +1,83 @@
-use crate::ai;
-use crate::grid::{get_winner, Grid, Mark};
+use crate::input;
+use std::error::Error;
+use std::fmt;
 use std::io;
-use std::thread::sleep;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, sleep};
 use std::time::Duration;
+use ttt::ai::{self, Engine};
+use ttt::book::OpeningBook;
+use ttt::error::GameError;
+use ttt::game::{Game, GameResult, Player};
+use ttt::gomoku;
+use ttt::gravity;
+use ttt::grid::{Grid, Mark};
+use ttt::mcts::{MonteCarloEngine, MonteCarloGame};
+use ttt::multiplayer;
+use ttt::notakto::NotaktoEngine;
+use ttt::notakto::{self, NotaktoGame};
+use ttt::obstacles;
+use ttt::opening::{self, SideChoice};
+use ttt::order_chaos;
+use ttt::paranoid::ParanoidSearch;
+use ttt::quantum::{self, QuantumGame, QuantumMove};
+use ttt::qubic;
+use ttt::random::Random;
+use ttt::table::{PerfectPlayTable, TableEngine};
+use ttt::ultimate::{self, UltimateGame};
+
+pub const MAX_GRID_SIZE: usize = 50;
+
+static ACTIVE_SEARCH: Mutex<Option<ai::StopToken>> = Mutex::new(None);
+
+/// Stops the AI search if one is running, returns false otherwise.
+pub fn interrupt_search() -> bool {
+    match ACTIVE_SEARCH.lock().unwrap().as_ref() {
+        Some(token) => {
+            token.stop();
+            true
+        }
+        None => false,
+    }
+}
 
 pub enum StateType {
     Startup(StartupStateData),
     PlayerTurn(PlayerTurnStateData),
     AiTurn(AiTurnStateData),
     OutcomeCheck(OutcomeCheckStateData),
+    OpeningPlacement(OpeningPlacementStateData),
+    SideChoice(SideChoiceStateData),
+    UltimateTurn(UltimateTurnStateData),
+    QuantumTurn(QuantumTurnStateData),
+    NotaktoTurn(NotaktoTurnStateData),
+    MultiPlayerTurn(MultiPlayerTurnStateData),
+}
+
+#[derive(Default, Clone)]
+pub struct Options {
+    pub show_stats: bool,
+    pub book: Option<Arc<OpeningBook>>,
+    pub table: Option<Arc<PerfectPlayTable>>,
 }
 
 impl StateType {
-    pub fn initial_state() -> StateType {
-        StateType::Startup(StartupStateData {})
+    pub fn initial_state(options: Options) -> StateType {
+        StateType::Startup(StartupStateData { options })
     }
-    pub fn to_next_state(self) -> Option<StateType> {
+    pub fn into_next_state(self) -> Option<StateType> {
         match self {
             StateType::Startup(s) => next_state(s),
             StateType::PlayerTurn(s) => next_state(s),
             StateType::AiTurn(s) => next_state(s),
             StateType::OutcomeCheck(s) => next_state(s),
+            StateType::OpeningPlacement(s) => next_state(s),
+            StateType::SideChoice(s) => next_state(s),
+            StateType::UltimateTurn(s) => next_state(s),
+            StateType::QuantumTurn(s) => next_state(s),
+            StateType::NotaktoTurn(s) => next_state(s),
+            StateType::MultiPlayerTurn(s) => next_state(s),
         }
     }
 }
@@ -33,52 +90,231 @@ fn next_state<T: GameState>(state: T) -> Option<StateType> {
     state.get_next_state()
 }
 
-pub struct StartupStateData;
+pub struct StartupStateData {
+    options: Options,
+}
 
 impl GameState for StartupStateData {
     fn get_next_state(self) -> Option<StateType> {
-        match create_new_game() {
-            Ok((grid, engine)) => {
-                println!("{}", &grid);
-                if engine.get_ai_side() == Mark::Cross {
-                    Some(StateType::AiTurn(AiTurnStateData { grid, engine }))
-                } else {
-                    Some(StateType::PlayerTurn(PlayerTurnStateData { grid, engine }))
-                }
+        let options = self.options;
+        match create_new_game(&options) {
+            Ok(NewGame::Ultimate(game, engine)) => {
+                println!("{}", game);
+                Some(StateType::UltimateTurn(UltimateTurnStateData {
+                    game,
+                    engine,
+                    options,
+                }))
+            }
+            Ok(NewGame::Quantum(game, engine)) => {
+                println!("{}", game);
+                Some(StateType::QuantumTurn(QuantumTurnStateData {
+                    game,
+                    engine,
+                    options,
+                }))
+            }
+            Ok(NewGame::Notakto(game, engine)) => {
+                println!("{}", game);
+                Some(StateType::NotaktoTurn(NotaktoTurnStateData {
+                    game,
+                    engine,
+                    options,
+                }))
+            }
+            Ok(NewGame::MultiPlayer(game, engines, player_side)) => {
+                println!("{}", game.get_grid());
+                Some(StateType::MultiPlayerTurn(MultiPlayerTurnStateData {
+                    game,
+                    engines,
+                    player_side,
+                    options,
+                }))
+            }
+            Ok(NewGame::OnGrid(game, engine, Some(protocol))) if protocol.has_side_choice() => {
+                println!("{}", game.get_grid());
+                println!("X places two crosses and a nought, then O picks a side");
+                let by_ai = engine.get_ai_side() == Player::FIRST;
+                Some(StateType::OpeningPlacement(OpeningPlacementStateData {
+                    game,
+                    engine,
+                    options,
+                    protocol,
+                    by_ai,
+                    remaining: opening::OPENING_MARKS,
+                }))
+            }
+            Ok(NewGame::OnGrid(game, engine, _)) => {
+                println!("{}", game.get_grid());
+                Some(first_turn(game, engine, options))
             }
             Err(e) => {
                 println!("{}! 😡", e);
-                Some(StateType::Startup(StartupStateData {}))
+                Some(StateType::Startup(StartupStateData { options }))
             }
         }
     }
 }
 
+/// Whoever's turn it is once the opening is over.
+fn first_turn(game: Game, engine: Box<dyn Engine>, options: Options) -> StateType {
+    if game.get_side_to_move() == engine.get_ai_side() {
+        StateType::AiTurn(AiTurnStateData {
+            game,
+            engine,
+            options,
+        })
+    } else {
+        StateType::PlayerTurn(PlayerTurnStateData {
+            game,
+            engine,
+            options,
+        })
+    }
+}
+
+/// Placing the marks of both sides before the side choice of Swap and Swap2.
+pub struct OpeningPlacementStateData {
+    game: Game,
+    engine: Box<dyn Engine>,
+    options: Options,
+    protocol: opening::Protocol,
+    by_ai: bool,
+    remaining: usize,
+}
+
+impl GameState for OpeningPlacementStateData {
+    fn get_next_state(mut self) -> Option<StateType> {
+        if self.remaining == 0 {
+            let may_place_more = self.protocol == opening::Protocol::Swap2
+                && self.game.get_move_count() == opening::OPENING_MARKS;
+            return Some(StateType::SideChoice(SideChoiceStateData {
+                game: self.game,
+                engine: self.engine,
+                options: self.options,
+                by_ai: !self.by_ai,
+                may_place_more,
+            }));
+        }
+
+        let side = self.game.get_side_to_move();
+        let what = self.game.get_mark(side);
+        let result = if self.by_ai {
+            sleep(Duration::from_secs(1));
+            match opening::ai_opening_mark(&self.game) {
+                Some(pos) => self.game.make_move(what, pos).map_err(|e| e.into()),
+                None => Err(GameError::GameOver.into()),
+            }
+        } else {
+            println!("Place a {:?} for the opening!", what);
+            make_player_move(&mut self.game, side)
+        };
+        match result {
+            Ok(_) => {
+                println!("{}", self.game.get_grid());
+                self.remaining -= 1;
+            }
+            Err(e) => println!("{}! 😡", e),
+        }
+        Some(StateType::OpeningPlacement(self))
+    }
+}
+
+/// Choosing sides after the opening marks of Swap and Swap2 are placed.
+pub struct SideChoiceStateData {
+    game: Game,
+    engine: Box<dyn Engine>,
+    options: Options,
+    by_ai: bool,
+    may_place_more: bool,
+}
+
+impl GameState for SideChoiceStateData {
+    fn get_next_state(self) -> Option<StateType> {
+        let choice = if self.by_ai {
+            opening::ai_side_choice(&self.game, self.may_place_more)
+        } else {
+            match side_choice_prompt(self.may_place_more) {
+                Ok(choice) => choice,
+                Err(e) => {
+                    println!("{}! 😡", e);
+                    return Some(StateType::SideChoice(self));
+                }
+            }
+        };
+        match choice {
+            SideChoice::Take(side) => {
+                let ai_side = if self.by_ai { side } else { side.opponent() };
+                println!("AI side is {:?}", self.game.get_mark(ai_side));
+                let engine = limited_engine(ai_side, gomoku::SEARCH_DEPTH);
+                Some(first_turn(self.game, engine, self.options))
+            }
+            SideChoice::PlaceMore => {
+                if self.by_ai {
+                    println!("AI places two more marks, then you pick a side");
+                }
+                Some(StateType::OpeningPlacement(OpeningPlacementStateData {
+                    game: self.game,
+                    engine: self.engine,
+                    options: self.options,
+                    protocol: opening::Protocol::Swap2,
+                    by_ai: self.by_ai,
+                    remaining: opening::EXTRA_MARKS,
+                }))
+            }
+        }
+    }
+}
+
+fn side_choice_prompt(may_place_more: bool) -> io::Result<SideChoice> {
+    if may_place_more {
+        println!("Choose side [X/O] or [P]lace two more marks:");
+    } else {
+        println!("Choose side [X/O]:");
+    }
+    let choice_str = input::read_line()?;
+
+    match choice_str.trim() {
+        "X" | "x" => Ok(SideChoice::Take(Player::FIRST)),
+        "O" | "o" => Ok(SideChoice::Take(Player::SECOND)),
+        "P" | "p" if may_place_more => Ok(SideChoice::PlaceMore),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid choice",
+        )),
+    }
+}
+
 pub struct PlayerTurnStateData {
-    grid: Grid,
-    engine: ai::AlphaBetaPruning,
+    game: Game,
+    engine: Box<dyn Engine>,
+    options: Options,
 }
 
 impl GameState for PlayerTurnStateData {
     fn get_next_state(mut self) -> Option<StateType> {
         println!("Make your move!");
-        let player_side = match self.engine.get_ai_side() {
-            Mark::Cross => Mark::Nought,
-            Mark::Nought => Mark::Cross,
-        };
-        match make_player_move(&mut self.grid, player_side) {
+        let player_side = self.engine.get_ai_side().opponent();
+        let snapshot = self.game.clone();
+        let game = &mut self.game;
+        let result = ponder_during(self.engine.as_mut(), &snapshot, || {
+            make_player_move(game, player_side)
+        });
+        match result {
             Ok(_) => {
-                println!("{}", self.grid);
+                println!("{}", self.game.get_grid());
                 Some(StateType::AiTurn(AiTurnStateData {
-                    grid: self.grid,
+                    game: self.game,
                     engine: self.engine,
+                    options: self.options,
                 }))
             }
             Err(e) => {
                 println!("{}! 😡", e);
                 Some(StateType::PlayerTurn(PlayerTurnStateData {
-                    grid: self.grid,
+                    game: self.game,
                     engine: self.engine,
+                    options: self.options,
                 }))
             }
         }
@@ -86,154 +322,788 @@ impl GameState for PlayerTurnStateData {
 }
 
 pub struct AiTurnStateData {
-    grid: Grid,
-    engine: ai::AlphaBetaPruning,
+    game: Game,
+    engine: Box<dyn Engine>,
+    options: Options,
 }
 
 impl GameState for AiTurnStateData {
     fn get_next_state(mut self) -> Option<StateType> {
         sleep(Duration::from_secs(1));
-        if self.engine.try_make_move(&mut self.grid) {
-            println!("{}", self.grid);
+        println!("Thinking... (type 'stop' or press Ctrl-C to hurry me up)");
+        if let Ok(stats) = make_ai_move(self.engine.as_mut(), &mut self.game) {
+            println!("{}", self.game.get_grid());
+            if self.options.show_stats {
+                println!("Search stats: {}", stats);
+            }
         }
         Some(StateType::OutcomeCheck(OutcomeCheckStateData {
-            grid: self.grid,
+            game: self.game,
             engine: self.engine,
+            options: self.options,
         }))
     }
 }
 
+/// Either side's turn in ultimate tic-tac-toe, which has its own board.
+pub struct UltimateTurnStateData {
+    game: UltimateGame,
+    engine: MonteCarloEngine,
+    options: Options,
+}
+
+impl GameState for UltimateTurnStateData {
+    fn get_next_state(mut self) -> Option<StateType> {
+        let game = &mut self.game;
+        if play_board_turn(
+            game,
+            &mut self.engine,
+            &self.options,
+            make_ultimate_player_move,
+        ) {
+            return next_game(self.options);
+        }
+        Some(StateType::UltimateTurn(self))
+    }
+}
+
+/// Either side's turn in quantum tic-tac-toe, including the collapse of
+/// cycles.
+pub struct QuantumTurnStateData {
+    game: QuantumGame,
+    engine: MonteCarloEngine,
+    options: Options,
+}
+
+impl GameState for QuantumTurnStateData {
+    fn get_next_state(mut self) -> Option<StateType> {
+        if let Some(GameResult::Win(winner)) = self.game.get_result() {
+            let loser = winner.opponent();
+            println!(
+                "{:?} scores {}, {:?} scores {}",
+                winner.standard_mark(),
+                self.game.get_score(winner),
+                loser.standard_mark(),
+                self.game.get_score(loser)
+            );
+        }
+        let game = &mut self.game;
+        if play_board_turn(
+            game,
+            &mut self.engine,
+            &self.options,
+            make_quantum_player_move,
+        ) {
+            return next_game(self.options);
+        }
+        Some(StateType::QuantumTurn(self))
+    }
+}
+
+/// Either side's turn in Notakto, where both sides place crosses.
+pub struct NotaktoTurnStateData {
+    game: NotaktoGame,
+    engine: NotaktoEngine,
+    options: Options,
+}
+
+impl GameState for NotaktoTurnStateData {
+    fn get_next_state(mut self) -> Option<StateType> {
+        if self.game.is_over() {
+            println!("The last board is dead, so whoever killed it loses");
+        }
+        let game = &mut self.game;
+        if play_board_turn(
+            game,
+            &mut self.engine,
+            &self.options,
+            make_notakto_player_move,
+        ) {
+            return next_game(self.options);
+        }
+        Some(StateType::NotaktoTurn(self))
+    }
+}
+
+/// Any player's turn in games of more than two players, where the AI takes
+/// all the seats but one.
+pub struct MultiPlayerTurnStateData {
+    game: Game,
+    engines: Vec<Box<dyn Engine>>,
+    player_side: Player,
+    options: Options,
+}
+
+impl GameState for MultiPlayerTurnStateData {
+    fn get_next_state(mut self) -> Option<StateType> {
+        if check_finished(&self.game, self.player_side) {
+            return next_game(self.options);
+        }
+
+        let side = self.game.get_side_to_move();
+        let engine = self
+            .engines
+            .iter_mut()
+            .find(|engine| engine.get_ai_side() == side);
+        if let Some(engine) = engine {
+            sleep(Duration::from_secs(1));
+            println!(
+                "{} is thinking... (type 'stop' or press Ctrl-C to hurry it up)",
+                self.game.get_mark(side).symbol()
+            );
+            if let Ok(stats) = make_ai_move(engine.as_mut(), &mut self.game) {
+                println!("{}", self.game.get_grid());
+                if self.options.show_stats {
+                    println!("Search stats: {}", stats);
+                }
+            }
+        } else {
+            println!("Make your move!");
+            match make_player_move(&mut self.game, side) {
+                Ok(_) => println!("{}", self.game.get_grid()),
+                Err(e) => println!("{}! 😡", e),
+            }
+        }
+        Some(StateType::MultiPlayerTurn(self))
+    }
+}
+
 pub struct OutcomeCheckStateData {
-    grid: Grid,
-    engine: ai::AlphaBetaPruning,
+    game: Game,
+    engine: Box<dyn Engine>,
+    options: Options,
 }
 
 impl GameState for OutcomeCheckStateData {
     fn get_next_state(self) -> Option<StateType> {
-        if check_finished(&self.grid, self.engine.get_ai_side()) {
-            if should_continue() {
-                Some(StateType::Startup(StartupStateData {}))
-            } else {
-                None
-            }
+        if check_finished(&self.game, self.engine.get_ai_side().opponent()) {
+            next_game(self.options)
         } else {
             Some(StateType::PlayerTurn(PlayerTurnStateData {
-                grid: self.grid,
+                game: self.game,
                 engine: self.engine,
+                options: self.options,
             }))
         }
     }
 }
 
-fn create_new_game() -> io::Result<(Grid, ai::AlphaBetaPruning)> {
-    const MAX_GRID_SIZE: usize = 50;
+enum NewGame {
+    OnGrid(Game, Box<dyn Engine>, Option<opening::Protocol>),
+    Ultimate(UltimateGame, MonteCarloEngine),
+    Quantum(QuantumGame, MonteCarloEngine),
+    Notakto(NotaktoGame, NotaktoEngine),
+    MultiPlayer(Game, Vec<Box<dyn Engine>>, Player),
+}
+
+#[derive(Copy, Clone)]
+enum Variant {
+    TicTacToe,
+    Wild,
+    Gomoku(gomoku::Rules),
+    ConnectFour,
+    Qubic,
+    Ultimate,
+    Quantum,
+    Notakto,
+    OrderAndChaos,
+    MultiPlayer,
+}
+
+fn create_new_game(options: &Options) -> Result<NewGame, Box<dyn Error>> {
     println!("\nWelcome to Tic-Tac-Toe 🙃");
 
-    fn grid_size_prompt() -> io::Result<usize> {
-        println!("Enter grid side length (e.g 3 for 3x3 grid):");
-        let size_str: String = {
-            let mut size_str = String::new();
-            io::stdin().read_line(&mut size_str)?;
-            size_str
-                .trim()
-                .chars()
-                .take_while(|c| c.is_digit(10))
-                .collect()
-        };
-        match size_str.parse::<usize>() {
-            Ok(size_as_usize @ 2..=MAX_GRID_SIZE) => Ok(size_as_usize),
+    fn variant_prompt() -> io::Result<Variant> {
+        println!(
+            "Choose game [T]ic-tac-toe / [W]ild tic-tac-toe / Gomoku [F]reestyle / Gomoku [S]tandard / [R]enju / [C]onnect Four / [Q]ubic / [U]ltimate / Qu[a]ntum / [N]otakto / [O]rder and Chaos / [M]ulti-player:"
+        );
+        let variant_str = input::read_line()?;
+
+        match variant_str.trim() {
+            "T" | "t" => Ok(Variant::TicTacToe),
+            "W" | "w" => Ok(Variant::Wild),
+            "F" | "f" => Ok(Variant::Gomoku(gomoku::Rules::Freestyle)),
+            "S" | "s" => Ok(Variant::Gomoku(gomoku::Rules::Standard)),
+            "R" | "r" => Ok(Variant::Gomoku(gomoku::Rules::Renju)),
+            "C" | "c" => Ok(Variant::ConnectFour),
+            "Q" | "q" => Ok(Variant::Qubic),
+            "U" | "u" => Ok(Variant::Ultimate),
+            "A" | "a" => Ok(Variant::Quantum),
+            "N" | "n" => Ok(Variant::Notakto),
+            "O" | "o" => Ok(Variant::OrderAndChaos),
+            "M" | "m" => Ok(Variant::MultiPlayer),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid game")),
+        }
+    }
+
+    fn grid_size_prompt(default: Option<usize>) -> Result<usize, Box<dyn Error>> {
+        match default {
+            Some(size) => println!("Enter grid side length (empty for {0}x{0} grid):", size),
+            None => println!("Enter grid side length (e.g 3 for 3x3 grid):"),
+        }
+        let size_str: String = input::read_line()?
+            .trim()
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        match (size_str.parse::<usize>(), default) {
+            (Ok(size_as_usize @ 2..=MAX_GRID_SIZE), _) => Ok(size_as_usize),
+            (Ok(size_as_usize), _) => Err(GameError::InvalidSize(size_as_usize).into()),
+            (_, Some(size)) if size_str.is_empty() => Ok(size),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid grid size").into()),
+        }
+    }
+
+    fn win_length_prompt(default: usize) -> io::Result<usize> {
+        println!("Enter win length (empty for {}):", default);
+        let length_str = input::read_line()?;
+
+        match length_str.trim() {
+            "" => Ok(default),
+            length_str => length_str
+                .parse::<usize>()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid win length")),
+        }
+    }
+
+    fn player_count_prompt() -> io::Result<usize> {
+        println!(
+            "Enter number of players, 3 or 4 (empty for {}):",
+            multiplayer::DEFAULT_PLAYER_COUNT
+        );
+        let count_str = input::read_line()?;
+
+        match count_str.trim() {
+            "" => Ok(multiplayer::DEFAULT_PLAYER_COUNT),
+            "3" => Ok(3),
+            "4" => Ok(4),
             _ => Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
-                "Invalid grid size",
+                "Invalid number of players",
             )),
         }
     }
 
-    let grid_size = grid_size_prompt()?;
-    println!("Grid size is {}", grid_size);
+    fn board_count_prompt() -> io::Result<usize> {
+        println!(
+            "Enter number of boards (empty for {}):",
+            notakto::DEFAULT_BOARD_COUNT
+        );
+        let count_str = input::read_line()?;
+
+        match count_str.trim() {
+            "" => Ok(notakto::DEFAULT_BOARD_COUNT),
+            count_str => count_str.parse::<usize>().map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, "Invalid number of boards")
+            }),
+        }
+    }
+
+    fn move_first_prompt() -> io::Result<bool> {
+        println!("Both sides place crosses. Move first? [Y/N]:");
+        let answer = input::read_line()?;
 
-    fn ai_side_prompt() -> io::Result<Mark> {
+        match answer.trim() {
+            "Y" | "y" => Ok(true),
+            "N" | "n" => Ok(false),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Inappropriate answer",
+            )),
+        }
+    }
+
+    fn misere_prompt() -> io::Result<bool> {
+        println!("Misère rules, where completing a line loses? [Y/N]:");
+        let answer = input::read_line()?;
+
+        match answer.trim() {
+            "Y" | "y" => Ok(true),
+            "N" | "n" => Ok(false),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Inappropriate answer",
+            )),
+        }
+    }
+
+    fn topology_prompt() -> io::Result<bool> {
+        println!(
+            "Choose board [F]lat / [T]oroidal, where rows wrap around the edges (empty for flat):"
+        );
+        let topology_str = input::read_line()?;
+
+        match topology_str.trim() {
+            "" | "F" | "f" => Ok(false),
+            "T" | "t" => Ok(true),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid board")),
+        }
+    }
+
+    fn obstacles_prompt(grid: &mut Grid) -> Result<(), Box<dyn Error>> {
+        println!(
+            "Enter the number of blocked squares to place at random, or their positions as x,y pairs (empty for none):"
+        );
+        let obstacles_str = input::read_line()?;
+
+        if let Ok(count) = obstacles_str.trim().parse::<usize>() {
+            obstacles::block_random_cells(grid, count, &mut Random::from_time())?;
+            return Ok(());
+        }
+        let cells = obstacles_str
+            .split_whitespace()
+            .map(|pair| {
+                let (x, y) = pair.split_once(',')?;
+                Some((x.parse().ok()?, y.parse().ok()?))
+            })
+            .collect::<Option<Vec<(usize, usize)>>>()
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "Invalid blocked squares")
+            })?;
+        Ok(obstacles::block_cells(grid, &cells)?)
+    }
+
+    fn tic_tac_toe_game() -> Result<Game, Box<dyn Error>> {
+        let mut grid = Grid::new(grid_size_prompt(None)?);
+        grid.set_toroidal(topology_prompt()?);
+        obstacles_prompt(&mut grid)?;
+        Ok(Game::from_grid(grid)?)
+    }
+
+    let variant = variant_prompt()?;
+    let mut game = match variant {
+        Variant::TicTacToe => {
+            let mut game = tic_tac_toe_game()?;
+            game.set_misere(misere_prompt()?);
+            game
+        }
+        Variant::Wild => {
+            let mut game = tic_tac_toe_game()?;
+            game.set_wild(true);
+            game
+        }
+        Variant::Gomoku(rules) => {
+            let grid_size = grid_size_prompt(Some(gomoku::DEFAULT_SIDE_LENGTH))?;
+            gomoku::new_game(grid_size, rules)?
+        }
+        Variant::ConnectFour => gravity::new_game(
+            gravity::DEFAULT_WIDTH,
+            gravity::DEFAULT_HEIGHT,
+            win_length_prompt(gravity::DEFAULT_WIN_LENGTH)?,
+        )?,
+        Variant::Qubic => qubic::new_game(grid_size_prompt(Some(qubic::DEFAULT_SIDE_LENGTH))?)?,
+        Variant::Ultimate => {
+            let ai_side = ai_side_prompt()?;
+            println!("AI side is {:?}", ai_side.standard_mark());
+            let engine = MonteCarloEngine::new(ai_side);
+            return Ok(NewGame::Ultimate(UltimateGame::new(), engine));
+        }
+        Variant::Quantum => {
+            let ai_side = ai_side_prompt()?;
+            println!("AI side is {:?}", ai_side.standard_mark());
+            let engine = MonteCarloEngine::new(ai_side);
+            return Ok(NewGame::Quantum(QuantumGame::new(), engine));
+        }
+        Variant::Notakto => {
+            let game = NotaktoGame::new(board_count_prompt()?)?;
+            let ai_side = if move_first_prompt()? {
+                Player::SECOND
+            } else {
+                Player::FIRST
+            };
+            println!("AI is {}", ai_side);
+            return Ok(NewGame::Notakto(game, NotaktoEngine::new(ai_side)));
+        }
+        Variant::OrderAndChaos => order_chaos::new_game()?,
+        Variant::MultiPlayer => multiplayer::new_game(
+            player_count_prompt()?,
+            grid_size_prompt(Some(multiplayer::DEFAULT_SIDE_LENGTH))?,
+            win_length_prompt(multiplayer::DEFAULT_WIN_LENGTH)?,
+        )?,
+    };
+    let grid = game.get_grid();
+    let grid_size = grid.get_side_length();
+    if grid.get_depth() > 1 {
+        let (width, height, depth) = (grid_size, grid.get_height(), grid.get_depth());
+        println!("Grid size is {}x{}x{}", width, height, depth);
+    } else {
+        println!("Grid size is {}x{}", grid_size, grid.get_height());
+    }
+
+    fn player_side_prompt(game: &Game) -> io::Result<Player> {
+        let choices: Vec<String> = game
+            .players()
+            .map(|player| mark_key(game.get_mark(player)))
+            .collect();
+        println!("Choose side [{}]:", choices.join("/"));
+        let side_str = input::read_line()?;
+
+        game.players()
+            .find(|&player| mark_key(game.get_mark(player)).eq_ignore_ascii_case(side_str.trim()))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid side"))
+    }
+
+    if game.get_player_count() > 2 {
+        let player_side = player_side_prompt(&game)?;
+        println!("Your side is {}", game.get_mark(player_side).symbol());
+        let engines = game
+            .players()
+            .filter(|&side| side != player_side)
+            .map(|side| {
+                let engine = ParanoidSearch::new(side, multiplayer::SEARCH_DEPTH);
+                Box::new(engine) as Box<dyn Engine>
+            })
+            .collect();
+        return Ok(NewGame::MultiPlayer(game, engines, player_side));
+    }
+
+    fn protocol_prompt() -> io::Result<Option<opening::Protocol>> {
+        println!("Choose opening rule [N]one / [P]ro / [L]ong Pro / [S]wap / Swap[2]:");
+        let protocol_str = input::read_line()?;
+
+        match protocol_str.trim() {
+            "N" | "n" => Ok(None),
+            "P" | "p" => Ok(Some(opening::Protocol::Pro)),
+            "L" | "l" => Ok(Some(opening::Protocol::LongPro)),
+            "S" | "s" => Ok(Some(opening::Protocol::Swap)),
+            "2" => Ok(Some(opening::Protocol::Swap2)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid opening rule",
+            )),
+        }
+    }
+
+    let protocol = match variant {
+        Variant::Gomoku(_) => protocol_prompt()?,
+        _ => None,
+    };
+    if let Some(protocol) = protocol {
+        protocol.apply(&mut game)?;
+    }
+
+    fn ai_side_prompt() -> io::Result<Player> {
         println!("Choose side [X/O]:");
-        let side_str = {
-            let mut side_str = String::new();
-            io::stdin().read_line(&mut side_str)?;
-            side_str
-        };
+        let side_str = input::read_line()?;
 
         match side_str.trim() {
-            "X" | "x" => Ok(Mark::Nought),
-            "O" | "o" => Ok(Mark::Cross),
+            "X" | "x" => Ok(Player::SECOND),
+            "O" | "o" => Ok(Player::FIRST),
             _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid side")),
         }
     }
 
-    let ai_side = ai_side_prompt()?;
-    println!("AI side is {:?}", ai_side);
+    fn role_prompt() -> io::Result<Player> {
+        println!("Choose role [O]rder, who wants five in a row of either mark / [C]haos, who wants to fill the board without one:");
+        let role_str = input::read_line()?;
 
-    Ok((Grid::new(grid_size), ai::AlphaBetaPruning::new(ai_side)))
-}
+        match role_str.trim() {
+            "O" | "o" => Ok(order_chaos::CHAOS),
+            "C" | "c" => Ok(order_chaos::ORDER),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid role")),
+        }
+    }
+
+    let ai_side = match variant {
+        Variant::OrderAndChaos => role_prompt()?,
+        _ => ai_side_prompt()?,
+    };
+    if game.is_order_and_chaos() {
+        println!("AI role is {}", order_chaos::role_name(ai_side));
+    } else {
+        println!("AI side is {:?}", game.get_mark(ai_side));
+    }
 
-fn make_player_move(grid: &mut Grid, player_side: Mark) -> io::Result<()> {
-    fn index_prompt(grid_side_length: usize, name: &str) -> io::Result<usize> {
-        let mut col_str = String::new();
-        io::stdin().read_line(&mut col_str)?;
-        let index = match col_str.trim().parse::<usize>() {
-            Ok(index) => index,
-            _ => {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    format!("Invalid {} index", name),
-                ))
+    let engine: Box<dyn Engine> = match (&options.table, variant) {
+        // the table is solved for the standard rules on a plain grid
+        (Some(table), Variant::TicTacToe)
+            if table.get_side_length() == grid_size
+                && game.has_standard_rules()
+                && game.get_grid().is_plain() =>
+        {
+            Box::new(TableEngine::new(ai_side, table.clone()))
+        }
+        (_, Variant::Wild) => Box::new(ai::AlphaBetaPruning::new(ai_side)),
+        (_, Variant::TicTacToe) => {
+            let mut engine = ai::AlphaBetaPruning::new(ai_side);
+            if let Some(book) = &options.book {
+                engine.set_opening_book(book.clone());
             }
-        };
-        if index < grid_side_length {
-            Ok(index)
-        } else {
-            Err(io::Error::new(
+            Box::new(engine)
+        }
+        (_, Variant::Gomoku(_)) => limited_engine(ai_side, gomoku::SEARCH_DEPTH),
+        (_, Variant::ConnectFour) => limited_engine(ai_side, gravity::SEARCH_DEPTH),
+        (_, Variant::Qubic) => limited_engine(ai_side, qubic::SEARCH_DEPTH),
+        (_, Variant::OrderAndChaos) => limited_engine(ai_side, order_chaos::SEARCH_DEPTH),
+        (_, Variant::Ultimate)
+        | (_, Variant::Quantum)
+        | (_, Variant::Notakto)
+        | (_, Variant::MultiPlayer) => {
+            unreachable!("games off a single grid return early")
+        }
+    };
+    Ok(NewGame::OnGrid(game, engine, protocol))
+}
+
+/// Search engine for boards too large to solve.
+fn limited_engine(ai_side: Player, depth: usize) -> Box<dyn Engine> {
+    let mut engine = ai::AlphaBetaPruning::new(ai_side);
+    engine.set_depth_limit(Some(depth));
+    Box::new(engine)
+}
+
+/// The key naming the mark in prompts, where Δ and □ are hard to type.
+fn mark_key(mark: Mark) -> String {
+    match mark {
+        Mark::Triangle => "T".to_string(),
+        Mark::Square => "S".to_string(),
+        _ => mark.symbol().to_string(),
+    }
+}
+
+fn index_prompt(grid_side_length: usize, name: &str) -> Result<usize, Box<dyn Error>> {
+    let col_str = input::read_line()?;
+    let index = match col_str.trim().parse::<usize>() {
+        Ok(index) => index,
+        _ => {
+            return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
-                format!("Invalid {} index {}", name, index),
-            ))
+                format!("Invalid {} index", name),
+            )
+            .into())
+        }
+    };
+    if index < grid_side_length {
+        Ok(index)
+    } else {
+        Err(GameError::OutOfBounds.into())
+    }
+}
+
+fn make_player_move(game: &mut Game, player_side: Player) -> Result<(), Box<dyn Error>> {
+    fn mark_prompt() -> io::Result<Mark> {
+        println!("Enter mark to place [X/O]:");
+        let mark_str = input::read_line()?;
+
+        match mark_str.trim() {
+            "X" | "x" => Ok(Mark::Cross),
+            "O" | "o" => Ok(Mark::Nought),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid mark")),
         }
     }
+    let what = if game.placeable_marks().count() > 1 {
+        mark_prompt()?
+    } else {
+        game.get_mark(player_side)
+    };
+
     println!("Enter column index for your next move:");
-    let col = index_prompt(grid.get_side_length(), "column")?;
+    let col = index_prompt(game.get_grid().get_width(), "column")?;
+
+    let pos = if game.has_gravity() {
+        game.drop_position(col)?
+    } else {
+        println!("Enter row index for your next move:");
+        let row = index_prompt(game.get_grid().get_height(), "row")?;
+        let layer = match game.get_grid().get_depth() {
+            1 => 0,
+            depth => {
+                println!("Enter layer index for your next move:");
+                index_prompt(depth, "layer")?
+            }
+        };
+        game.get_grid().position_3d(col, row, layer)?
+    };
+    game.make_move(what, pos)?;
+    Ok(())
+}
 
+fn make_ultimate_player_move(
+    game: &mut UltimateGame,
+    player_side: Player,
+) -> Result<(), Box<dyn Error>> {
+    let side_length = ultimate::SIDE_LENGTH * ultimate::SIDE_LENGTH;
+    println!("Make your move on a dotted board!");
+    println!("Enter column index for your next move:");
+    let col = index_prompt(side_length, "column")?;
     println!("Enter row index for your next move:");
-    let row = index_prompt(grid.get_side_length(), "row")?;
+    let row = index_prompt(side_length, "row")?;
+    let m = game.position(col, row)?;
+    game.make_move(player_side.standard_mark(), m)?;
+    Ok(())
+}
 
-    match grid.set_at_pos(col, row, player_side) {
-        Ok(()) => Ok(()),
-        Err(mark) => Err(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            format!("Square already contains {:?}", mark),
-        )),
+fn make_quantum_player_move(
+    game: &mut QuantumGame,
+    player_side: Player,
+) -> Result<(), Box<dyn Error>> {
+    fn cell_prompt(game: &QuantumGame, which: &str) -> Result<usize, Box<dyn Error>> {
+        println!("Enter column index for {}:", which);
+        let col = index_prompt(quantum::SIDE_LENGTH, "column")?;
+        println!("Enter row index for {}:", which);
+        let row = index_prompt(quantum::SIDE_LENGTH, "row")?;
+        Ok(game.cell(col, row)?)
     }
+
+    let m = match game.legal_moves()[..] {
+        [QuantumMove::Collapse(_), _] => {
+            let turn = game.get_cycle().unwrap_or_default();
+            println!(
+                "Mark {} closed a cycle, pick the square it collapses to!",
+                turn
+            );
+            QuantumMove::Collapse(cell_prompt(game, "the collapse")?)
+        }
+        [QuantumMove::Classical(cell)] => {
+            println!("Only one square is left, it gets your classical mark");
+            QuantumMove::Classical(cell)
+        }
+        _ => {
+            println!("Make your move!");
+            let first = cell_prompt(game, "your first spooky mark")?;
+            let second = cell_prompt(game, "your second spooky mark")?;
+            QuantumMove::Entangle(first, second)
+        }
+    };
+    game.make_move(player_side, m)?;
+    Ok(())
 }
 
-fn check_finished(grid: &Grid, ai_side: Mark) -> bool {
-    if let Some(winner) = get_winner(&grid) {
-        if winner == ai_side {
-            println!("Condolences, you lost 😰");
-        } else {
+fn make_notakto_player_move(
+    game: &mut NotaktoGame,
+    player_side: Player,
+) -> Result<(), Box<dyn Error>> {
+    println!("Make your move on a live board!");
+    println!("Enter board index for your next move:");
+    let board = index_prompt(game.get_boards().len(), "board")?;
+    println!("Enter column index for your next move:");
+    let col = index_prompt(notakto::SIDE_LENGTH, "column")?;
+    println!("Enter row index for your next move:");
+    let row = index_prompt(notakto::SIDE_LENGTH, "row")?;
+    let m = game.position(board, col, row)?;
+    game.make_move(player_side, m)?;
+    Ok(())
+}
+
+/// Lets the engine think about the next AI move while the action runs.
+fn ponder_during<T>(engine: &mut dyn Engine, game: &Game, action: impl FnOnce() -> T) -> T {
+    let stop_token = engine.get_stop_token();
+    stop_token.reset();
+    thread::scope(|scope| {
+        scope.spawn(|| engine.ponder(game));
+        let result = action();
+        stop_token.stop();
+        result
+    })
+}
+
+fn make_ai_move<G>(engine: &mut dyn Engine<G>, game: &mut G) -> Result<ai::SearchStats, GameError> {
+    let stop_token = engine.get_stop_token();
+    let search_done = AtomicBool::new(false);
+    *ACTIVE_SEARCH.lock().unwrap() = Some(stop_token.clone());
+
+    let stats = thread::scope(|scope| {
+        scope.spawn(|| {
+            // anything else is meant for the prompts after the AI move
+            let mut unread = Vec::new();
+            while !search_done.load(Ordering::Relaxed) {
+                match input::read_line_timeout(Duration::from_millis(100)) {
+                    Some(Ok(line)) if line.trim() == "stop" => stop_token.stop(),
+                    Some(Ok(line)) => unread.push(line),
+                    Some(Err(_)) => break,
+                    None => {}
+                }
+            }
+            input::unread_lines(unread);
+        });
+        let stats = engine.try_make_move(game);
+        search_done.store(true, Ordering::Relaxed);
+        stats
+    });
+
+    *ACTIVE_SEARCH.lock().unwrap() = None;
+    stats
+}
+
+fn check_finished(game: &Game, player_side: Player) -> bool {
+    if game.is_misere() && matches!(game.get_result(), Some(GameResult::Win(_))) {
+        println!("A line is complete, so its owner loses");
+    }
+    if game.is_order_and_chaos() && game.get_result() == Some(GameResult::Win(order_chaos::CHAOS)) {
+        println!("The board is full without a line, so Chaos wins");
+    }
+    announce_result(game.get_result(), player_side)
+}
+
+fn announce_result(result: Option<GameResult>, player_side: Player) -> bool {
+    match result {
+        Some(GameResult::Win(winner)) if winner == player_side => {
             println!("Congratulations, you won! 😱");
+            true
+        }
+        Some(GameResult::Win(_)) => {
+            println!("Condolences, you lost 😰");
+            true
+        }
+        Some(GameResult::Draw) => {
+            println!("It's a draw! 😲");
+            true
         }
-        true
-    } else if grid.is_full() {
-        println!("It's a draw! 😲");
-        true
+        None => false,
+    }
+}
+
+/// Asks the player for their move in the given game.
+type PlayerMove<G> = fn(&mut G, Player) -> Result<(), Box<dyn Error>>;
+
+/// Plays one turn of a game with a board of its own: the engine's move,
+/// or the player's asked for with the given function. Returns whether the
+/// game was over instead, after announcing the result.
+fn play_board_turn<G>(
+    game: &mut G,
+    engine: &mut dyn Engine<G>,
+    options: &Options,
+    make_player_move: PlayerMove<G>,
+) -> bool
+where
+    G: MonteCarloGame + fmt::Display,
+{
+    let ai_side = engine.get_ai_side();
+    if announce_result(game.get_result(), ai_side.opponent()) {
+        return true;
+    }
+
+    if game.get_side_to_move() == ai_side {
+        sleep(Duration::from_secs(1));
+        println!("Thinking... (type 'stop' or press Ctrl-C to hurry me up)");
+        if let Ok(stats) = make_ai_move(engine, game) {
+            println!("{}", game);
+            if options.show_stats {
+                println!("Search stats: {}", stats);
+            }
+        }
+    } else {
+        match make_player_move(game, ai_side.opponent()) {
+            Ok(_) => println!("{}", game),
+            Err(e) => println!("{}! 😡", e),
+        }
+    }
+    false
+}
+
+/// Starts over if the player wants another game.
+fn next_game(options: Options) -> Option<StateType> {
+    if should_continue() {
+        Some(StateType::Startup(StartupStateData { options }))
     } else {
-        false
+        None
     }
 }
 
 fn should_continue() -> bool {
     fn continue_prompt() -> io::Result<bool> {
         println!("One more game? [Y/N]:");
-        let answer = {
-            let mut answer = String::new();
-            io::stdin().read_line(&mut answer)?;
-            answer
-        };
+        let answer = input::read_line()?;
 
         match answer.trim() {
             "Y" | "y" => Ok(true),
@@ -0,0 +1,649 @@
+use crate::error::GameError;
+use crate::grid::{get_winner, Grid, Mark, Position};
+use crate::opening;
+use crate::order_chaos::{CHAOS, ORDER};
+use crate::renju;
+use std::fmt;
+
+/// Boards at least this large list the moves next to existing marks first,
+/// as those are the ones that matter in the fight for a line.
+pub const NEAR_STONES_FIRST_FROM: usize = 5;
+
+/// Lines with more marks weigh no more than this many in the evaluation,
+/// which keeps the sum over all lines of even the largest grids below 2^40.
+const MAX_WEIGHED_MARKS: usize = 10;
+
+/// The side making the moves, known by their seat in the turn order. The
+/// marks they may place are up to the rules: their own one in standard
+/// games, and any in wild games.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Player(usize);
+
+impl Player {
+    pub const FIRST: Player = Player(0);
+    pub const SECOND: Player = Player(1);
+
+    /// The player in the given seat, counting from zero.
+    pub fn new(seat: usize) -> Player {
+        Player(seat)
+    }
+
+    /// Counts from zero for the first player.
+    pub fn get_seat(self) -> usize {
+        self.0
+    }
+
+    /// The other player of a game of two.
+    pub fn opponent(self) -> Player {
+        if self == Player::FIRST {
+            Player::SECOND
+        } else {
+            Player::FIRST
+        }
+    }
+
+    /// The mark of the player in games where the players keep to the marks
+    /// in the order of Mark::ALL, as in all games made by Game::new.
+    pub fn standard_mark(self) -> Mark {
+        Mark::ALL[self.0]
+    }
+}
+
+impl fmt::Display for Player {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "player {}", self.0 + 1)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    Win(Player),
+    Draw,
+}
+
+/// A grid together with the rules of playing on it: crosses move first,
+/// the sides take turns, and no moves are allowed once the game is over.
+#[derive(Clone, Debug)]
+pub struct Game {
+    grid: Grid,
+    /// The own mark of every player, by seat.
+    marks: Vec<Mark>,
+    side_to_move: Player,
+    move_count: usize,
+    result: Option<GameResult>,
+    history: Vec<Position>,
+    renju: bool,
+    min_third_move_distance: Option<usize>,
+    gravity: bool,
+    misere: bool,
+    wild: bool,
+    order_and_chaos: bool,
+}
+
+impl Game {
+    pub fn new(side_length: usize) -> Game {
+        Game::with_marks(Grid::new(side_length), vec![Mark::Cross, Mark::Nought])
+    }
+
+    fn with_marks(grid: Grid, marks: Vec<Mark>) -> Game {
+        Game {
+            grid,
+            marks,
+            side_to_move: Player::FIRST,
+            move_count: 0,
+            result: None,
+            history: Vec::new(),
+            renju: false,
+            min_third_move_distance: None,
+            gravity: false,
+            misere: false,
+            wild: false,
+            order_and_chaos: false,
+        }
+    }
+
+    /// Continues a game from the given grid. The side to move is deduced
+    /// from the number of marks of each side.
+    pub fn from_grid(grid: Grid) -> Result<Game, GameError> {
+        Game::with_players(grid, &[Mark::Cross, Mark::Nought])
+    }
+
+    /// Continues a game of players with the given marks of their own, who
+    /// take turns in the order listed.
+    pub fn with_players(grid: Grid, marks: &[Mark]) -> Result<Game, GameError> {
+        let distinct = marks
+            .iter()
+            .enumerate()
+            .all(|(i, mark)| !marks[..i].contains(mark));
+        if marks.len() < 2 || !distinct {
+            return Err(GameError::InvalidPlayers);
+        }
+        if let Some(other) = grid
+            .positions()
+            .filter_map(|pos| grid.get(pos))
+            .find(|mark| !marks.contains(mark))
+        {
+            return Err(GameError::WrongMark(other));
+        }
+        let counts: Vec<usize> = marks
+            .iter()
+            .map(|&mark| {
+                grid.positions()
+                    .filter(|&pos| grid.get(pos) == Some(mark))
+                    .count()
+            })
+            .collect();
+        // everyone has moved as often as the first player or once less
+        let first = counts[0];
+        if let Some(i) = (1..counts.len()).find(|&i| counts[i] > counts[i - 1]) {
+            return Err(GameError::WrongSide(Player(i)));
+        }
+        if first > counts[counts.len() - 1] + 1 {
+            return Err(GameError::WrongSide(Player::FIRST));
+        }
+        let mut game = Game::with_marks(grid, marks.to_vec());
+        game.side_to_move = Player(counts.iter().position(|&count| count < first).unwrap_or(0));
+        game.move_count = counts.iter().sum();
+        game.result = game.check_result();
+        Ok(game)
+    }
+
+    /// Continues a wild game of crosses and noughts from the given grid. As
+    /// both players may have placed either mark, the side to move is deduced
+    /// from the number of marks alone.
+    pub fn wild_from_grid(grid: Grid) -> Result<Game, GameError> {
+        let marks = vec![Mark::Cross, Mark::Nought];
+        let placed: Vec<Mark> = grid.positions().filter_map(|pos| grid.get(pos)).collect();
+        if let Some(&other) = placed.iter().find(|mark| !marks.contains(mark)) {
+            return Err(GameError::WrongMark(other));
+        }
+        let mut game = Game::with_marks(grid, marks);
+        game.side_to_move = Player(placed.len() % 2);
+        game.move_count = placed.len();
+        game.set_wild(true);
+        Ok(game)
+    }
+
+    pub fn get_grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// The players in the order they take turns.
+    pub fn players(&self) -> impl Iterator<Item = Player> {
+        (0..self.marks.len()).map(Player)
+    }
+
+    pub fn get_player_count(&self) -> usize {
+        self.marks.len()
+    }
+
+    /// The own mark of the player.
+    pub fn get_mark(&self, player: Player) -> Mark {
+        self.marks[player.0]
+    }
+
+    /// The player whose own mark it is, if any.
+    pub fn owner(&self, mark: Mark) -> Option<Player> {
+        self.marks.iter().position(|&m| m == mark).map(Player)
+    }
+
+    pub fn get_side_to_move(&self) -> Player {
+        self.side_to_move
+    }
+
+    /// The own mark of the side to move.
+    pub fn get_mark_to_move(&self) -> Mark {
+        self.get_mark(self.side_to_move)
+    }
+
+    /// Who moves after the given player.
+    pub fn next_player(&self, player: Player) -> Player {
+        Player((player.0 + 1) % self.marks.len())
+    }
+
+    fn previous_player(&self, player: Player) -> Player {
+        Player((player.0 + self.marks.len() - 1) % self.marks.len())
+    }
+
+    pub fn get_move_count(&self) -> usize {
+        self.move_count
+    }
+
+    pub fn get_result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// Forbids Cross the moves listed in the renju module.
+    pub fn set_renju(&mut self, renju: bool) {
+        self.renju = renju;
+    }
+
+    pub fn is_renju(&self) -> bool {
+        self.renju
+    }
+
+    /// Makes Cross open in the center and keep its second mark at least
+    /// the given distance away from it, as the Pro opening rules require.
+    pub fn set_min_third_move_distance(&mut self, distance: Option<usize>) {
+        self.min_third_move_distance = distance;
+    }
+
+    /// Makes marks drop to the lowest empty cell of their column.
+    pub fn set_gravity(&mut self, gravity: bool) {
+        self.gravity = gravity;
+    }
+
+    pub fn has_gravity(&self) -> bool {
+        self.gravity
+    }
+
+    /// Makes completing a line lose the game instead of winning it.
+    pub fn set_misere(&mut self, misere: bool) {
+        self.misere = misere;
+        self.result = self.check_result();
+    }
+
+    pub fn is_misere(&self) -> bool {
+        self.misere
+    }
+
+    /// Lets the players place any of their marks; whoever completes a line
+    /// of any wins.
+    pub fn set_wild(&mut self, wild: bool) {
+        self.wild = wild;
+        self.result = self.check_result();
+    }
+
+    pub fn is_wild(&self) -> bool {
+        self.wild
+    }
+
+    /// Gives Order, who moves first, and Chaos the roles of the game of that
+    /// name: both place either mark, any line wins for Order, and a full
+    /// grid wins for Chaos.
+    pub fn set_order_and_chaos(&mut self, order_and_chaos: bool) {
+        self.order_and_chaos = order_and_chaos;
+        self.result = self.check_result();
+    }
+
+    pub fn is_order_and_chaos(&self) -> bool {
+        self.order_and_chaos
+    }
+
+    fn places_any_mark(&self) -> bool {
+        self.wild || self.order_and_chaos
+    }
+
+    /// Whether none of the rule variants is on, so that the books and tables
+    /// made for standard play apply.
+    pub fn has_standard_rules(&self) -> bool {
+        !self.renju
+            && self.min_third_move_distance.is_none()
+            && !self.gravity
+            && !self.misere
+            && !self.wild
+            && !self.order_and_chaos
+    }
+
+    /// Iterates over the marks the side to move may place, its own first.
+    pub fn placeable_marks(&self) -> impl Iterator<Item = Mark> + '_ {
+        let own = self.get_mark_to_move();
+        let any = self.places_any_mark();
+        let others = self.marks.iter().filter(move |&&mark| any && mark != own);
+        std::iter::once(own).chain(others.copied())
+    }
+
+    /// Where a mark dropped into the column lands.
+    pub fn drop_position(&self, column: usize) -> Result<Position, GameError> {
+        let grid = &self.grid;
+        grid.position(column, 0)?;
+        (0..grid.get_height())
+            .rev()
+            .filter_map(|row| grid.position(column, row).ok())
+            .find(|&pos| grid.is_empty_cell(pos))
+            .ok_or(GameError::ColumnFull)
+    }
+
+    /// Checks whether the side to move is forbidden to play at the empty
+    /// position by gravity, the opening or the Renju rules.
+    pub fn is_forbidden(&self, pos: Position) -> bool {
+        self.floats(pos)
+            || self.breaks_opening(pos)
+            || (self.renju
+                && self.side_to_move == Player::FIRST
+                && renju::is_forbidden(&self.grid, pos))
+    }
+
+    fn floats(&self, pos: Position) -> bool {
+        self.gravity
+            && self
+                .grid
+                .position_3d(pos.x(), pos.y() + 1, pos.z())
+                .is_ok_and(|below| self.grid.is_empty_cell(below))
+    }
+
+    fn breaks_opening(&self, pos: Position) -> bool {
+        let distance = match self.min_third_move_distance {
+            Some(distance) => distance,
+            None => return false,
+        };
+        let from_center = opening::distance_from_center(&self.grid, pos);
+        match self.move_count {
+            0 => from_center != 0,
+            2 => from_center < distance,
+            _ => false,
+        }
+    }
+
+    /// Iterates over the moves the side to move may make; none once the game
+    /// is over. See NEAR_STONES_FIRST_FROM for the order on large boards.
+    pub fn legal_moves(&self) -> impl Iterator<Item = Position> + '_ {
+        let grid = &self.grid;
+        let near_first = grid.get_side_length() >= NEAR_STONES_FIRST_FROM;
+        let over = self.is_over();
+        let (near, far): (Vec<Position>, Vec<Position>) = grid
+            .empty_cells()
+            .filter(|_| !over)
+            .partition(|&pos| near_first && grid.is_near_stone(pos));
+        near.into_iter()
+            .chain(far)
+            .filter(move |&pos| !self.is_forbidden(pos))
+    }
+
+    pub fn make_move(&mut self, what: Mark, pos: Position) -> Result<(), GameError> {
+        if self.result.is_some() {
+            return Err(GameError::GameOver);
+        }
+        if !self.placeable_marks().any(|mark| mark == what) {
+            return Err(GameError::WrongMark(what));
+        }
+        let player = self.side_to_move;
+        if self.grid.is_empty_cell(pos) && self.is_forbidden(pos) {
+            return Err(GameError::ForbiddenMove);
+        }
+        self.grid.set(pos, what)?;
+        self.side_to_move = self.next_player(player);
+        self.move_count += 1;
+        self.history.push(pos);
+        // only rows through the new mark can have been completed
+        self.result = if self.grid.completes_line(pos, what) {
+            Some(GameResult::Win(self.line_winner(player)))
+        } else if self.grid.is_full() {
+            Some(self.full_grid_result())
+        } else {
+            None
+        };
+        Ok(())
+    }
+
+    /// Takes back the last move made on this object, returns its position.
+    pub fn undo_move(&mut self) -> Option<Position> {
+        let pos = self.history.pop()?;
+        self.grid.unset(pos);
+        self.side_to_move = self.previous_player(self.side_to_move);
+        self.move_count -= 1;
+        self.result = None;
+        Some(pos)
+    }
+
+    /// Who wins when the given player completes a line: the next player
+    /// under misère rules, and Order whoever it is in Order and Chaos.
+    pub fn line_winner(&self, player: Player) -> Player {
+        if self.order_and_chaos {
+            ORDER
+        } else if self.misere {
+            self.next_player(player)
+        } else {
+            player
+        }
+    }
+
+    /// Rough measure of how promising the grid is for the given player,
+    /// negative when it favours the others. Every line held by marks of one
+    /// kind counts for the player completing it would make win, four times
+    /// more with each extra mark up to MAX_WEIGHED_MARKS. Lines of wild games
+    /// belong to nobody.
+    pub fn evaluate(&self, player: Player, lines: &[Vec<Position>]) -> i64 {
+        let mut score = 0;
+        for line in lines {
+            let mut marks = line.iter().filter_map(|&pos| self.grid.get(pos));
+            let mark = match marks.next() {
+                Some(mark) => mark,
+                None => continue,
+            };
+            let count = marks.try_fold(1, |count, other| (other == mark).then_some(count + 1));
+            let winner = self
+                .owner(mark)
+                .filter(|_| !self.wild)
+                .map(|owner| self.line_winner(owner));
+            if let (Some(count), Some(winner)) = (count, winner) {
+                let weight = 1 << (2 * count.min(MAX_WEIGHED_MARKS));
+                score += if winner == player { weight } else { -weight };
+            }
+        }
+        score
+    }
+
+    fn full_grid_result(&self) -> GameResult {
+        if self.order_and_chaos {
+            GameResult::Win(CHAOS)
+        } else {
+            GameResult::Draw
+        }
+    }
+
+    fn check_result(&self) -> Option<GameResult> {
+        // the grid tells who completed the line by the mark in standard
+        // games, and otherwise only who moved last
+        if let Some(what) = get_winner(&self.grid) {
+            let player = self
+                .owner(what)
+                .filter(|_| !self.places_any_mark())
+                .unwrap_or_else(|| self.previous_player(self.side_to_move));
+            Some(GameResult::Win(self.line_winner(player)))
+        } else if self.grid.is_full() {
+            Some(self.full_grid_result())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameError::*, Mark::*, *};
+
+    fn play(game: &mut Game, what: Mark, x: usize, y: usize) -> Result<(), GameError> {
+        let pos = game.get_grid().position(x, y)?;
+        game.make_move(what, pos)
+    }
+
+    #[test]
+    fn test_sides_take_turns() {
+        let mut game = Game::new(3);
+        assert_eq!(Player::FIRST, game.get_side_to_move());
+        assert_eq!(Err(WrongMark(Nought)), play(&mut game, Nought, 0, 0));
+
+        play(&mut game, Cross, 0, 0).unwrap();
+        assert_eq!(Player::SECOND, game.get_side_to_move());
+        assert_eq!(Err(WrongMark(Cross)), play(&mut game, Cross, 1, 1));
+        assert_eq!(Err(OccupiedCell(Cross)), play(&mut game, Nought, 0, 0));
+
+        play(&mut game, Nought, 1, 1).unwrap();
+        assert_eq!(Player::FIRST, game.get_side_to_move());
+        assert_eq!(2, game.get_move_count());
+    }
+
+    #[test]
+    fn test_no_moves_after_win() {
+        let mut game = Game::new(3);
+        for &(x, y) in &[(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)] {
+            assert_eq!(None, game.get_result());
+            let what = game.get_mark_to_move();
+            play(&mut game, what, x, y).unwrap();
+        }
+        assert_eq!(Some(GameResult::Win(Player::FIRST)), game.get_result());
+        assert_eq!(Err(GameOver), play(&mut game, Nought, 2, 2));
+    }
+
+    #[test]
+    fn test_draw_when_full() {
+        let mut game = Game::new(3);
+        let moves = [
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (1, 1),
+            (0, 1),
+            (2, 1),
+            (1, 2),
+            (0, 2),
+        ];
+        for &(x, y) in &moves {
+            let what = game.get_mark_to_move();
+            play(&mut game, what, x, y).unwrap();
+        }
+        assert_eq!(None, game.get_result());
+        play(&mut game, Cross, 2, 2).unwrap();
+        assert_eq!(Some(GameResult::Draw), game.get_result());
+        assert_eq!(9, game.get_move_count());
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        let mut game = Game::new(3);
+        play(&mut game, Cross, 1, 1).unwrap();
+        let moves: Vec<usize> = game.legal_moves().map(|pos| pos.ind()).collect();
+        assert_eq!(vec![0, 1, 2, 3, 5, 6, 7, 8], moves);
+
+        for &(x, y) in &[(0, 1), (0, 0), (2, 1), (2, 2)] {
+            let what = game.get_mark_to_move();
+            play(&mut game, what, x, y).unwrap();
+        }
+        assert!(game.is_over());
+        assert_eq!(0, game.legal_moves().count());
+    }
+
+    #[test]
+    fn test_legal_moves_near_stones_first() {
+        let mut game = Game::new(NEAR_STONES_FIRST_FROM);
+        play(&mut game, Cross, 0, 0).unwrap();
+        let moves: Vec<(usize, usize)> = game.legal_moves().map(|pos| (pos.x(), pos.y())).collect();
+
+        assert_eq!(game.get_grid().get_size() - 1, moves.len());
+        assert_eq!(vec![(1, 0), (0, 1), (1, 1), (2, 0)], moves[..4]);
+    }
+
+    #[test]
+    fn test_undo_move() {
+        let mut game = Game::new(3);
+        for &(x, y) in &[(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)] {
+            let what = game.get_mark_to_move();
+            play(&mut game, what, x, y).unwrap();
+        }
+        assert!(game.is_over());
+
+        assert_eq!(game.get_grid().position(2, 0).ok(), game.undo_move());
+        assert_eq!(None, game.get_result());
+        assert_eq!(Player::FIRST, game.get_side_to_move());
+        assert_eq!(4, game.get_move_count());
+        assert_eq!(Ok(None), game.get_grid().get_at_pos(2, 0));
+    }
+
+    #[test]
+    fn test_from_grid() {
+        let mut g = Grid::new(3);
+        g.set_at_pos(1, 1, Cross).unwrap();
+        let game = Game::from_grid(g.clone()).unwrap();
+        assert_eq!(Player::SECOND, game.get_side_to_move());
+        assert_eq!(1, game.get_move_count());
+
+        g.set_at_pos(0, 0, Cross).unwrap();
+        assert_eq!(WrongSide(Player::FIRST), Game::from_grid(g).unwrap_err());
+
+        let mut g = Grid::new(3);
+        g.set_at_pos(1, 1, Nought).unwrap();
+        assert_eq!(WrongSide(Player::SECOND), Game::from_grid(g).unwrap_err());
+    }
+
+    #[test]
+    fn test_wild_player_completing_line_wins() {
+        let mut game = Game::new(3);
+        assert_eq!(vec![Cross], game.placeable_marks().collect::<Vec<_>>());
+        game.set_wild(true);
+        assert_eq!(
+            vec![Cross, Nought],
+            game.placeable_marks().collect::<Vec<_>>()
+        );
+
+        // noughts are placed by both players, the second one completes the row
+        let moves = [
+            (Nought, 0, 0),
+            (Cross, 0, 1),
+            (Nought, 1, 0),
+            (Nought, 2, 0),
+        ];
+        for &(what, x, y) in &moves {
+            assert_eq!(None, game.get_result());
+            play(&mut game, what, x, y).unwrap();
+        }
+        assert_eq!(Some(GameResult::Win(Player::SECOND)), game.get_result());
+        assert_eq!(Player::FIRST, game.get_side_to_move());
+    }
+
+    #[test]
+    fn test_wild_game_from_grid() {
+        // both players opened with a nought
+        let mut g = Grid::new(3);
+        g.set_at_pos(0, 0, Nought).unwrap();
+        g.set_at_pos(1, 0, Nought).unwrap();
+        assert_eq!(
+            WrongSide(Player::SECOND),
+            Game::from_grid(g.clone()).unwrap_err()
+        );
+
+        let mut game = Game::wild_from_grid(g).unwrap();
+        assert_eq!(Player::FIRST, game.get_side_to_move());
+        assert_eq!(2, game.get_move_count());
+        play(&mut game, Nought, 2, 0).unwrap();
+        assert_eq!(Some(GameResult::Win(Player::FIRST)), game.get_result());
+    }
+
+    #[test]
+    fn test_wild_line_counts_for_last_mover() {
+        // the first player has just completed the noughts' row
+        let mut g = Grid::new(3);
+        for &(x, y) in &[(0, 0), (1, 0), (2, 0)] {
+            g.set_at_pos(x, y, Nought).unwrap();
+        }
+        for &(x, y) in &[(0, 1), (1, 1), (0, 2), (2, 2)] {
+            g.set_at_pos(x, y, Cross).unwrap();
+        }
+        let mut game = Game::from_grid(g.clone()).unwrap();
+        assert_eq!(Some(GameResult::Win(Player::SECOND)), game.get_result());
+        game.set_wild(true);
+        assert_eq!(Some(GameResult::Win(Player::FIRST)), game.get_result());
+        let game = Game::wild_from_grid(g).unwrap();
+        assert_eq!(Some(GameResult::Win(Player::FIRST)), game.get_result());
+    }
+
+    #[test]
+    fn test_misere_line_loses() {
+        let mut game = Game::new(3);
+        game.set_misere(true);
+        for &(x, y) in &[(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)] {
+            let what = game.get_mark_to_move();
+            play(&mut game, what, x, y).unwrap();
+        }
+        assert_eq!(Some(GameResult::Win(Player::SECOND)), game.get_result());
+
+        let mut game = Game::from_grid(game.get_grid().clone()).unwrap();
+        assert_eq!(Some(GameResult::Win(Player::FIRST)), game.get_result());
+        game.set_misere(true);
+        assert_eq!(Some(GameResult::Win(Player::SECOND)), game.get_result());
+    }
+}
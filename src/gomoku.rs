@@ -0,0 +1,127 @@
+//! Gomoku: five in a row on a large board.
+
+use crate::error::GameError;
+use crate::game::Game;
+use crate::grid::{Grid, WinCondition};
+
+pub const DEFAULT_SIDE_LENGTH: usize = 15;
+pub const WIN_LENGTH: usize = 5;
+
+/// How deep the AI searches on a Gomoku board, which is far too large to
+/// solve.
+pub const SEARCH_DEPTH: usize = 4;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Rules {
+    /// Five or more in a row win.
+    Freestyle,
+    /// Exactly five in a row win, overlines don't count.
+    Standard,
+    /// Freestyle for Nought, while Cross wins with exactly five and may not
+    /// make the shapes forbidden in the renju module.
+    Renju,
+}
+
+impl Rules {
+    pub fn win_condition(self) -> WinCondition {
+        WinCondition {
+            length: WIN_LENGTH,
+            overlines: self != Rules::Standard,
+        }
+    }
+}
+
+pub fn new_game(side_length: usize, rules: Rules) -> Result<Game, GameError> {
+    if side_length < WIN_LENGTH {
+        return Err(GameError::InvalidSize(side_length));
+    }
+    let mut game = Game::from_grid(Grid::with_win_condition(
+        side_length,
+        rules.win_condition(),
+    )?)?;
+    game.set_renju(rules == Rules::Renju);
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::GameError::*;
+    use crate::game::{GameResult, Player};
+    use crate::grid::Mark::*;
+
+    fn play_row(rules: Rules, length: usize) -> Option<GameResult> {
+        let mut game = new_game(DEFAULT_SIDE_LENGTH, rules).unwrap();
+        // crosses fill the row from both ends so the overline closes last
+        let mut xs: Vec<usize> = (0..length).collect();
+        xs.swap(WIN_LENGTH - 1, length - 1);
+        for (turn, &x) in xs.iter().enumerate() {
+            let cross = game.get_grid().position(x, 0).unwrap();
+            game.make_move(Cross, cross).unwrap();
+            if turn + 1 < length {
+                let nought = game.get_grid().position(2 * turn, 5).unwrap();
+                game.make_move(Nought, nought).unwrap();
+            }
+        }
+        game.get_result()
+    }
+
+    #[test]
+    fn test_five_in_a_row_wins() {
+        assert_eq!(
+            Some(GameResult::Win(Player::FIRST)),
+            play_row(Rules::Freestyle, 5)
+        );
+        assert_eq!(
+            Some(GameResult::Win(Player::FIRST)),
+            play_row(Rules::Standard, 5)
+        );
+    }
+
+    #[test]
+    fn test_overline_wins_only_in_freestyle() {
+        assert_eq!(
+            Some(GameResult::Win(Player::FIRST)),
+            play_row(Rules::Freestyle, 6)
+        );
+        assert_eq!(None, play_row(Rules::Standard, 6));
+    }
+
+    #[test]
+    fn test_renju_forbids_cross_double_three() {
+        let mut game = new_game(DEFAULT_SIDE_LENGTH, Rules::Renju).unwrap();
+        let moves = [
+            (5, 4),
+            (0, 0),
+            (6, 4),
+            (14, 0),
+            (7, 2),
+            (0, 14),
+            (7, 3),
+            (14, 14),
+        ];
+        for &(x, y) in &moves {
+            let pos = game.get_grid().position(x, y).unwrap();
+            game.make_move(game.get_mark_to_move(), pos).unwrap();
+        }
+        let forbidden = game.get_grid().position(7, 4).unwrap();
+
+        assert!(game.is_forbidden(forbidden));
+        assert!(game.legal_moves().all(|pos| pos != forbidden));
+        assert_eq!(Err(ForbiddenMove), game.make_move(Cross, forbidden));
+
+        // the same shape is fine for Nought
+        let elsewhere = game.get_grid().position(10, 10).unwrap();
+        game.make_move(Cross, elsewhere).unwrap();
+        assert!(!game.is_forbidden(forbidden));
+    }
+
+    #[test]
+    fn test_rejects_small_board() {
+        assert_eq!(
+            GameError::InvalidSize(4),
+            new_game(4, Rules::Freestyle).unwrap_err()
+        );
+        assert!(new_game(5, Rules::Standard).is_ok());
+    }
+}
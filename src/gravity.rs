@@ -0,0 +1,78 @@
+//! Connect Four style play: a move names a column and the mark drops to
+//! the lowest empty cell in it.
+
+use crate::error::GameError;
+use crate::game::Game;
+use crate::grid::{Grid, WinCondition};
+
+pub const DEFAULT_WIDTH: usize = 7;
+pub const DEFAULT_HEIGHT: usize = 6;
+pub const DEFAULT_WIN_LENGTH: usize = 4;
+
+/// How deep the AI searches on a Connect Four board, which is too large to
+/// solve in a reasonable time.
+pub const SEARCH_DEPTH: usize = 8;
+
+pub fn new_game(width: usize, height: usize, win_length: usize) -> Result<Game, GameError> {
+    let win_condition = WinCondition {
+        length: win_length,
+        overlines: true,
+    };
+    let mut game = Game::from_grid(Grid::rectangular(width, height, win_condition)?)?;
+    game.set_gravity(true);
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameResult, Player};
+    use crate::grid::Mark::*;
+
+    fn drop(game: &mut Game, column: usize) -> Result<(), GameError> {
+        let pos = game.drop_position(column)?;
+        game.make_move(game.get_mark_to_move(), pos)
+    }
+
+    #[test]
+    fn test_marks_drop_to_the_bottom() {
+        let mut game = new_game(DEFAULT_WIDTH, DEFAULT_HEIGHT, DEFAULT_WIN_LENGTH).unwrap();
+        drop(&mut game, 3).unwrap();
+        drop(&mut game, 3).unwrap();
+
+        let grid = game.get_grid();
+        assert_eq!(Ok(Some(Cross)), grid.get_at_pos(3, 5));
+        assert_eq!(Ok(Some(Nought)), grid.get_at_pos(3, 4));
+        let floating = grid.position(0, 0).unwrap();
+        assert_eq!(
+            Err(GameError::ForbiddenMove),
+            game.make_move(Cross, floating)
+        );
+        assert_eq!(Err(GameError::OutOfBounds), game.drop_position(7));
+    }
+
+    #[test]
+    fn test_legal_moves_are_column_drops() {
+        let mut game = new_game(DEFAULT_WIDTH, DEFAULT_HEIGHT, DEFAULT_WIN_LENGTH).unwrap();
+        for _ in 0..DEFAULT_HEIGHT {
+            drop(&mut game, 0).unwrap();
+        }
+        assert_eq!(Err(GameError::ColumnFull), game.drop_position(0));
+
+        let mut moves: Vec<(usize, usize)> =
+            game.legal_moves().map(|pos| (pos.x(), pos.y())).collect();
+        moves.sort_unstable();
+        assert_eq!(vec![(1, 5), (2, 5), (3, 5), (4, 5), (5, 5), (6, 5)], moves);
+    }
+
+    #[test]
+    fn test_four_in_a_row_wins() {
+        let mut game = new_game(DEFAULT_WIDTH, DEFAULT_HEIGHT, DEFAULT_WIN_LENGTH).unwrap();
+        for &column in &[0, 0, 1, 1, 2, 2] {
+            drop(&mut game, column).unwrap();
+        }
+        assert_eq!(None, game.get_result());
+        drop(&mut game, 3).unwrap();
+        assert_eq!(Some(GameResult::Win(Player::FIRST)), game.get_result());
+    }
+}
@@ -1,157 +1,516 @@
+use crate::error::GameError;
+use std::collections::HashSet;
 use std::fmt;
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+/// The marks of the players. Triangle and Square only take part in games
+/// of more than two players.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum Mark {
     Cross,
     Nought,
+    Triangle,
+    Square,
 }
 
-#[derive(Default, Debug)]
+impl Mark {
+    /// All marks, in the order the players take turns.
+    pub const ALL: [Mark; 4] = [Mark::Cross, Mark::Nought, Mark::Triangle, Mark::Square];
+
+    pub fn symbol(self) -> char {
+        match self {
+            Mark::Cross => 'X',
+            Mark::Nought => 'O',
+            Mark::Triangle => 'Δ',
+            Mark::Square => '□',
+        }
+    }
+}
+
+/// Content of a square. Blocked squares can't be played and break the rows
+/// running through them.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Cell {
+    Empty,
+    Marked(Mark),
+    Blocked,
+}
+
+/// Coordinates of a square. Positions can only be obtained from a grid that
+/// contains them, and are only meaningful for grids of the same dimensions,
+/// which debug builds check.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Position {
+    x: usize,
+    y: usize,
+    z: usize,
+    ind: usize,
+}
+
+impl Position {
+    pub fn x(&self) -> usize {
+        self.x
+    }
+
+    pub fn y(&self) -> usize {
+        self.y
+    }
+
+    /// The layer of the position, always 0 on flat grids.
+    pub fn z(&self) -> usize {
+        self.z
+    }
+
+    pub fn ind(&self) -> usize {
+        self.ind
+    }
+}
+
+/// How long a row of marks has to be to win the game.
+#[derive(Default, Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct WinCondition {
+    pub length: usize,
+    /// Whether rows longer than the length win as well.
+    pub overlines: bool,
+}
+
+impl WinCondition {
+    /// A row across the whole grid, as in classic tic-tac-toe.
+    pub fn full_line(side_length: usize) -> WinCondition {
+        WinCondition {
+            length: side_length,
+            overlines: true,
+        }
+    }
+
+    pub fn is_met(&self, row_length: usize) -> bool {
+        row_length == self.length || (self.overlines && row_length > self.length)
+    }
+}
+
+const WITHIN_LAYER: [(isize, isize, isize); 4] = [(1, 0, 0), (0, 1, 0), (1, 1, 0), (1, -1, 0)];
+
+const ACROSS_LAYERS: [(isize, isize, isize); 9] = [
+    (0, 0, 1),
+    (1, 0, 1),
+    (-1, 0, 1),
+    (0, 1, 1),
+    (0, -1, 1),
+    (1, 1, 1),
+    (1, -1, 1),
+    (-1, 1, 1),
+    (-1, -1, 1),
+];
+
+#[derive(Default, Clone, PartialEq, Eq, Hash, Debug)]
 pub struct Grid {
-    side_length: usize,
-    data: Vec<Option<Mark>>,
+    width: usize,
+    height: usize,
+    depth: usize,
+    win_condition: WinCondition,
+    toroidal: bool,
+    data: Vec<Cell>,
 }
 
 impl Grid {
     pub fn new(side_length: usize) -> Grid {
         Grid {
-            side_length,
-            data: vec![None; side_length * side_length],
+            width: side_length,
+            height: side_length,
+            depth: 1,
+            win_condition: WinCondition::full_line(side_length),
+            toroidal: false,
+            data: vec![Cell::Empty; side_length * side_length],
+        }
+    }
+
+    /// Creates a grid where rows shorter than the side length may win.
+    pub fn with_win_condition(
+        side_length: usize,
+        win_condition: WinCondition,
+    ) -> Result<Grid, GameError> {
+        Grid::rectangular(side_length, side_length, win_condition)
+    }
+
+    /// Creates a grid with the given number of columns and rows.
+    pub fn rectangular(
+        width: usize,
+        height: usize,
+        win_condition: WinCondition,
+    ) -> Result<Grid, GameError> {
+        if win_condition.length == 0 || win_condition.length > width.max(height) {
+            return Err(GameError::InvalidSize(win_condition.length));
         }
+        Ok(Grid {
+            width,
+            height,
+            depth: 1,
+            win_condition,
+            toroidal: false,
+            data: vec![Cell::Empty; width * height],
+        })
+    }
+
+    /// Creates a cube of side length layers, where rows may also run
+    /// through the layers, as in Qubic.
+    pub fn cube(side_length: usize, win_condition: WinCondition) -> Result<Grid, GameError> {
+        let mut grid = Grid::with_win_condition(side_length, win_condition)?;
+        grid.depth = side_length;
+        grid.data = vec![Cell::Empty; side_length.pow(3)];
+        Ok(grid)
     }
 
     pub fn get_size(&self) -> usize {
         self.data.len()
     }
 
+    /// Number of columns, which is the side length of square grids.
     pub fn get_side_length(&self) -> usize {
-        self.side_length
+        self.width
     }
 
-    pub fn get_at_ind(&self, ind: usize) -> Option<Mark> {
-        self.data[ind]
+    pub fn get_width(&self) -> usize {
+        self.width
     }
 
-    pub fn get_at_pos(&self, x: usize, y: usize) -> Option<Mark> {
-        self.get_at_ind(y * self.side_length + x)
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    /// Number of layers, which is 1 unless the grid is a cube.
+    pub fn get_depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn get_win_condition(&self) -> WinCondition {
+        self.win_condition
+    }
+
+    /// Makes rows run on across the edges of the grid, continuing from the
+    /// opposite edge as on the surface of a torus.
+    pub fn set_toroidal(&mut self, toroidal: bool) {
+        self.toroidal = toroidal;
+    }
+
+    pub fn is_toroidal(&self) -> bool {
+        self.toroidal
+    }
+
+    /// The position in the first layer.
+    pub fn position(&self, x: usize, y: usize) -> Result<Position, GameError> {
+        self.position_3d(x, y, 0)
+    }
+
+    pub fn position_3d(&self, x: usize, y: usize, z: usize) -> Result<Position, GameError> {
+        if x < self.width && y < self.height && z < self.depth {
+            Ok(Position {
+                x,
+                y,
+                z,
+                ind: (z * self.height + y) * self.width + x,
+            })
+        } else {
+            Err(GameError::OutOfBounds)
+        }
     }
 
-    pub fn set_at_ind(&mut self, ind: usize, what: Mark) -> Result<(), Mark> {
+    pub fn position_of_ind(&self, ind: usize) -> Result<Position, GameError> {
+        if ind < self.data.len() {
+            Ok(position_of_ind(self.width, self.height, ind))
+        } else {
+            Err(GameError::OutOfBounds)
+        }
+    }
+
+    /// Where the position is stored, which for a position of a grid of other
+    /// dimensions would be out of bounds or another square.
+    fn index(&self, pos: Position) -> usize {
+        debug_assert!(
+            pos.x < self.width
+                && pos.y < self.height
+                && pos.z < self.depth
+                && pos.ind == (pos.z * self.height + pos.y) * self.width + pos.x,
+            "{:?} is not on a {}x{}x{} grid",
+            pos,
+            self.width,
+            self.height,
+            self.depth
+        );
+        pos.ind
+    }
+
+    /// All squares of the grid, row by row and layer by layer.
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let (width, height) = (self.width, self.height);
+        (0..self.data.len()).map(move |ind| position_of_ind(width, height, ind))
+    }
+
+    /// The mark at the position, None for empty and blocked squares.
+    pub fn get(&self, pos: Position) -> Option<Mark> {
+        match self.data[self.index(pos)] {
+            Cell::Marked(what) => Some(what),
+            Cell::Empty | Cell::Blocked => None,
+        }
+    }
+
+    pub fn get_cell(&self, pos: Position) -> Cell {
+        self.data[self.index(pos)]
+    }
+
+    /// Whether the position may still be played, being neither marked nor
+    /// blocked.
+    pub fn is_empty_cell(&self, pos: Position) -> bool {
+        self.data[self.index(pos)] == Cell::Empty
+    }
+
+    pub fn set(&mut self, pos: Position, what: Mark) -> Result<(), GameError> {
+        self.fill(pos, Cell::Marked(what))
+    }
+
+    /// Removes the mark at the position. Blocked squares stay blocked.
+    pub fn unset(&mut self, pos: Position) {
+        let ind = self.index(pos);
+        let sqr = &mut self.data[ind];
+        if let Cell::Marked(_) = *sqr {
+            *sqr = Cell::Empty;
+        }
+    }
+
+    /// Blocks the empty position for the rest of the game.
+    pub fn block(&mut self, pos: Position) -> Result<(), GameError> {
+        self.fill(pos, Cell::Blocked)
+    }
+
+    fn fill(&mut self, pos: Position, cell: Cell) -> Result<(), GameError> {
+        let ind = self.index(pos);
         let sqr = &mut self.data[ind];
         match *sqr {
-            Some(side) => Err(side),
-            None => {
-                *sqr = Some(what);
+            Cell::Marked(side) => Err(GameError::OccupiedCell(side)),
+            Cell::Blocked => Err(GameError::BlockedCell),
+            Cell::Empty => {
+                *sqr = cell;
                 Ok(())
             }
         }
     }
 
-    pub fn set_at_pos(&mut self, x: usize, y: usize, what: Mark) -> Result<(), Mark> {
-        self.set_at_ind(y * self.side_length + x, what)
+    pub fn blocked_cells(&self) -> impl Iterator<Item = Position> + '_ {
+        self.positions()
+            .filter(move |&pos| self.data[self.index(pos)] == Cell::Blocked)
     }
 
-    pub fn unset_at_ind(&mut self, ind: usize) {
-        self.data[ind] = None;
+    /// Whether the grid is square and flat, has edges and no blocked squares
+    /// and is won by a row across it, the shape opening books and tablebases
+    /// are made for.
+    pub fn is_plain(&self) -> bool {
+        self.width == self.height
+            && self.depth == 1
+            && self.win_condition == WinCondition::full_line(self.width)
+            && !self.toroidal
+            && self.blocked_cells().next().is_none()
+    }
+
+    pub fn get_at_ind(&self, ind: usize) -> Result<Option<Mark>, GameError> {
+        Ok(self.get(self.position_of_ind(ind)?))
+    }
+
+    pub fn get_at_pos(&self, x: usize, y: usize) -> Result<Option<Mark>, GameError> {
+        Ok(self.get(self.position(x, y)?))
+    }
+
+    pub fn set_at_ind(&mut self, ind: usize, what: Mark) -> Result<(), GameError> {
+        self.set(self.position_of_ind(ind)?, what)
+    }
+
+    pub fn set_at_pos(&mut self, x: usize, y: usize, what: Mark) -> Result<(), GameError> {
+        self.set(self.position(x, y)?, what)
     }
 
     pub fn is_full(&self) -> bool {
-        self.data.iter().all(|e| e.is_some())
+        self.data.iter().all(|&e| e != Cell::Empty)
     }
-}
 
-impl fmt::Display for Grid {
-    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "\n    ")?;
-        for col in 0..self.side_length {
-            write!(formatter, "{:^3} ", col)?;
-        }
-        for row in 0..self.side_length {
-            write!(formatter, "\n{:^3}|", row)?;
-            for col in 0..self.side_length {
-                let symbol = match self.get_at_pos(col, row) {
-                    Some(Mark::Cross) => 'X',
-                    Some(Mark::Nought) => 'O',
-                    None => ' ',
-                };
-                write!(formatter, "{:^3}|", symbol)?;
-            }
-        }
-        write!(formatter, "\n")?;
-        Ok(())
+    /// Iterates over the cells without a mark or block, in index order.
+    pub fn empty_cells(&self) -> impl Iterator<Item = Position> + '_ {
+        self.positions().filter(move |&pos| self.is_empty_cell(pos))
     }
-}
 
-pub fn get_winner(g: &Grid) -> Option<Mark> {
-    fn get_col_winner(g: &Grid, col_ind: usize) -> Option<Mark> {
-        let first = g.data[col_ind];
-        let start_ind = col_ind + g.side_length;
-        let slice: &[Option<Mark>] = &g.data[start_ind..];
-        if slice.iter().step_by(g.side_length).all(|e| *e == first) {
-            first
-        } else {
-            None
-        }
+    /// Checks whether placing the mark at the position would complete a
+    /// winning row. The current content of the position is ignored.
+    pub fn completes_line(&self, pos: Position, what: Mark) -> bool {
+        self.rows_through(pos, what)
+            .any(|length| self.win_condition.is_met(length))
     }
-    for col_ind in 0..g.get_side_length() {
-        if let Some(w) = get_col_winner(g, col_ind) {
-            return Some(w);
-        }
+
+    /// Lengths of the rows of the mark through the position in every
+    /// direction, the position itself included.
+    fn rows_through(&self, pos: Position, what: Mark) -> impl Iterator<Item = usize> + '_ {
+        self.directions().map(move |&(dx, dy, dz)| {
+            // on a torus the row may come back around to the position
+            let limit = self.cycle_length((dx, dy, dz)) - 1;
+            let forward = self.run(pos, what, (dx, dy, dz), limit);
+            1 + forward + self.run(pos, what, (-dx, -dy, -dz), limit - forward)
+        })
     }
 
-    fn get_row_winner(g: &Grid, row_ind: usize) -> Option<Mark> {
-        let start_ind = row_ind * g.side_length;
-        let end_ind = start_ind + g.side_length;
-        let first = g.data[start_ind];
-        let slice = &g.data[start_ind + 1..end_ind];
-        if slice.iter().all(|e| *e == first) {
-            first
-        } else {
-            None
+    /// How many marks follow the position in the direction, at most limit.
+    fn run(
+        &self,
+        pos: Position,
+        what: Mark,
+        direction: (isize, isize, isize),
+        limit: usize,
+    ) -> usize {
+        let mut count = 0;
+        while count < limit {
+            match self.step(pos, direction, count + 1) {
+                Some(next) if self.data[self.index(next)] == Cell::Marked(what) => count += 1,
+                _ => break,
+            }
         }
+        count
     }
-    for row_ind in 0..g.get_side_length() {
-        if let Some(w) = get_row_winner(g, row_ind) {
-            return Some(w);
-        }
+
+    /// The position the given number of steps away in the direction, which
+    /// wraps around the edges of toroidal grids.
+    fn step(
+        &self,
+        pos: Position,
+        direction: (isize, isize, isize),
+        steps: usize,
+    ) -> Option<Position> {
+        let (dx, dy, dz) = direction;
+        let coordinate = |c: usize, d: isize, length: usize| {
+            let offset = d * steps as isize;
+            if self.toroidal {
+                Some((c as isize + offset).rem_euclid(length as isize) as usize)
+            } else {
+                c.checked_add_signed(offset)
+            }
+        };
+        self.position_3d(
+            coordinate(pos.x, dx, self.width)?,
+            coordinate(pos.y, dy, self.height)?,
+            coordinate(pos.z, dz, self.depth)?,
+        )
+        .ok()
     }
 
-    fn get_inc_diag_winner(g: &Grid) -> Option<Mark> {
-        let first = g.data[0];
-        let interval = g.side_length + 1;
-        let slice: &[Option<Mark>] = &g.data[interval..];
-        if slice.iter().step_by(interval).all(|e| *e == first) {
-            first
-        } else {
-            None
+    /// How many cells a row in the direction passes before it gets back to
+    /// where it started, which only happens on toroidal grids.
+    fn cycle_length(&self, direction: (isize, isize, isize)) -> usize {
+        if !self.toroidal {
+            return usize::MAX;
         }
+        let (dx, dy, dz) = direction;
+        [(dx, self.width), (dy, self.height), (dz, self.depth)]
+            .iter()
+            .filter(|&&(d, _)| d != 0)
+            .fold(1, |cycle, &(_, length)| cycle / gcd(cycle, length) * length)
     }
-    if let Some(w) = get_inc_diag_winner(g) {
-        return Some(w);
+
+    /// One direction of each line through a cell: those within the layer,
+    /// then on cubes those through the layers.
+    fn directions(&self) -> impl Iterator<Item = &'static (isize, isize, isize)> {
+        let across_layers: &[_] = if self.depth > 1 { &ACROSS_LAYERS } else { &[] };
+        WITHIN_LAYER.iter().chain(across_layers)
     }
 
-    fn get_mix_diag_winner(g: &Grid) -> Option<Mark> {
-        let interval = g.side_length - 1;
-        let start_ind = interval + interval;
-        let end_ind = g.data.len() - 1;
-        let first = g.data[interval];
-        let slice: &[Option<Mark>] = &g.data[start_ind..end_ind];
-        if slice.iter().step_by(interval).all(|e| *e == first) {
-            first
-        } else {
-            None
+    /// All rows of the winning length, as the positions along them. Rows
+    /// that wrap around onto themselves on a torus or run through blocked
+    /// squares are left out.
+    pub fn lines(&self) -> Vec<Vec<Position>> {
+        let length = self.win_condition.length;
+        let mut seen = HashSet::new();
+        self.positions()
+            .flat_map(|start| {
+                self.directions().filter_map(move |&direction| {
+                    (0..length)
+                        .map(|steps| self.step(start, direction, steps))
+                        .collect::<Option<Vec<Position>>>()
+                })
+            })
+            .filter(|line| {
+                let mut cells: Vec<usize> = line.iter().map(|pos| pos.ind).collect();
+                cells.sort_unstable();
+                cells.dedup();
+                cells.len() == length
+                    && line
+                        .iter()
+                        .all(|&pos| self.data[self.index(pos)] != Cell::Blocked)
+                    && seen.insert(cells)
+            })
+            .collect()
+    }
+
+    /// Checks whether any of the cells around the position is marked, those
+    /// in the neighbouring layers and across the edges of a torus included.
+    pub fn is_near_stone(&self, pos: Position) -> bool {
+        let layers = if self.depth > 1 { -1..=1 } else { 0..=0 };
+        layers
+            .flat_map(|dz| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| (dx, dy, dz))))
+            .filter_map(|direction| self.step(pos, direction, 1))
+            .any(|near| near.ind != pos.ind && self.get(near).is_some())
+    }
+}
+
+/// Draws cubes layer by layer.
+impl fmt::Display for Grid {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        for layer in 0..self.depth {
+            if self.depth > 1 {
+                write!(formatter, "\nLayer {}", layer)?;
+            }
+            write!(formatter, "\n    ")?;
+            for col in 0..self.width {
+                write!(formatter, "{:^3} ", col)?;
+            }
+            for row in 0..self.height {
+                write!(formatter, "\n{:^3}|", row)?;
+                for col in 0..self.width {
+                    let ind = (layer * self.height + row) * self.width + col;
+                    let symbol = match self.data[ind] {
+                        Cell::Marked(what) => what.symbol(),
+                        Cell::Blocked => '#',
+                        Cell::Empty => ' ',
+                    };
+                    write!(formatter, "{:^3}|", symbol)?;
+                }
+            }
+            writeln!(formatter)?;
         }
+        Ok(())
     }
-    if let Some(w) = get_mix_diag_winner(g) {
-        return Some(w);
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
     }
+}
 
-    None
+fn position_of_ind(width: usize, height: usize, ind: usize) -> Position {
+    Position {
+        x: ind % width,
+        y: ind / width % height,
+        z: ind / (width * height),
+        ind,
+    }
+}
+
+/// Returns the side owning a winning row, if any.
+pub fn get_winner(g: &Grid) -> Option<Mark> {
+    g.positions().find_map(|pos| {
+        let what = g.get(pos)?;
+        g.completes_line(pos, what).then_some(what)
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::{Mark::*, *};
+    use GameError::*;
 
     #[test]
     fn test_constructed_grid_is_empty() {
@@ -159,7 +518,7 @@ mod tests {
         assert_eq!(5, g.get_side_length());
         for i in 0..g.get_side_length() {
             for j in 0..g.get_side_length() {
-                assert_eq!(None, g.get_at_pos(i, j));
+                assert_eq!(Ok(None), g.get_at_pos(i, j));
             }
         }
     }
@@ -168,14 +527,14 @@ mod tests {
     fn test_set_cross() {
         let mut g = Grid::new(3);
         g.set_at_pos(1, 1, Cross).unwrap();
-        assert_eq!(Cross, g.set_at_pos(1, 1, Cross).unwrap_err());
-        assert_eq!(Cross, g.set_at_pos(1, 1, Nought).unwrap_err());
+        assert_eq!(OccupiedCell(Cross), g.set_at_pos(1, 1, Cross).unwrap_err());
+        assert_eq!(OccupiedCell(Cross), g.set_at_pos(1, 1, Nought).unwrap_err());
 
         for i in 0..g.get_side_length() {
             for j in 0..g.get_side_length() {
                 match (i, j) {
-                    (1, 1) => assert_eq!(Some(Cross), g.get_at_pos(1, 1)),
-                    _ => assert_eq!(None, g.get_at_pos(i, j)),
+                    (1, 1) => assert_eq!(Ok(Some(Cross)), g.get_at_pos(1, 1)),
+                    _ => assert_eq!(Ok(None), g.get_at_pos(i, j)),
                 }
             }
         }
@@ -185,19 +544,111 @@ mod tests {
     fn test_set_nought() {
         let mut g = Grid::new(3);
         g.set_at_pos(0, 2, Nought).unwrap();
-        assert_eq!(Nought, g.set_at_pos(0, 2, Nought).unwrap_err());
-        assert_eq!(Nought, g.set_at_pos(0, 2, Cross).unwrap_err());
+        assert_eq!(
+            OccupiedCell(Nought),
+            g.set_at_pos(0, 2, Nought).unwrap_err()
+        );
+        assert_eq!(OccupiedCell(Nought), g.set_at_pos(0, 2, Cross).unwrap_err());
 
         for i in 0..g.get_side_length() {
             for j in 0..g.get_side_length() {
                 match (i, j) {
-                    (0, 2) => assert_eq!(Some(Nought), g.get_at_pos(0, 2)),
-                    _ => assert_eq!(None, g.get_at_pos(i, j)),
+                    (0, 2) => assert_eq!(Ok(Some(Nought)), g.get_at_pos(0, 2)),
+                    _ => assert_eq!(Ok(None), g.get_at_pos(i, j)),
                 }
             }
         }
     }
 
+    #[test]
+    fn test_set_out_of_bounds() {
+        let mut g = Grid::new(3);
+        assert_eq!(OutOfBounds, g.set_at_ind(9, Cross).unwrap_err());
+        assert_eq!(OutOfBounds, g.set_at_pos(0, 3, Nought).unwrap_err());
+        assert!(g.data.iter().all(|&e| e == Cell::Empty));
+    }
+
+    #[test]
+    fn test_positions_are_bounds_checked() {
+        let g = Grid::new(3);
+        assert_eq!(OutOfBounds, g.position(3, 0).unwrap_err());
+        assert_eq!(OutOfBounds, g.position(0, 3).unwrap_err());
+        assert_eq!(OutOfBounds, g.position_of_ind(9).unwrap_err());
+        assert_eq!(OutOfBounds, g.get_at_pos(3, 1).unwrap_err());
+
+        let pos = g.position(2, 1).unwrap();
+        assert_eq!((2, 1, 5), (pos.x(), pos.y(), pos.ind()));
+        assert_eq!(pos, g.position_of_ind(5).unwrap());
+        assert_eq!(9, g.positions().count());
+        assert_eq!(Some(pos), g.positions().nth(5));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "is not on a 2x4x1 grid")]
+    fn test_position_of_other_dimensions() {
+        let wide = Grid::rectangular(4, 2, WinCondition::full_line(2)).unwrap();
+        let tall = Grid::rectangular(2, 4, WinCondition::full_line(2)).unwrap();
+        // in bounds of the tall grid, but stored where (1, 2) is
+        let pos = wide.position(1, 1).unwrap();
+        tall.get(pos);
+    }
+
+    #[test]
+    fn test_empty_cells() {
+        let mut g = Grid::new(3);
+        g.set_at_pos(1, 0, Cross).unwrap();
+        g.set_at_pos(2, 2, Nought).unwrap();
+
+        let cells: Vec<usize> = g.empty_cells().map(|pos| pos.ind()).collect();
+        assert_eq!(vec![0, 2, 3, 4, 5, 6, 7], cells);
+    }
+
+    #[test]
+    fn test_is_near_stone() {
+        let mut g = Grid::new(5);
+        g.set_at_pos(4, 0, Cross).unwrap();
+
+        assert!(g.is_near_stone(g.position(3, 1).unwrap()));
+        assert!(g.is_near_stone(g.position(4, 1).unwrap()));
+        assert!(!g.is_near_stone(g.position(4, 0).unwrap()));
+        assert!(!g.is_near_stone(g.position(2, 0).unwrap()));
+        assert!(!g.is_near_stone(g.position(0, 1).unwrap()));
+    }
+
+    #[test]
+    fn test_completes_line() {
+        let mut g = Grid::new(3);
+        g.set_at_pos(0, 2, Cross).unwrap();
+        g.set_at_pos(1, 1, Cross).unwrap();
+        g.set_at_pos(1, 0, Nought).unwrap();
+
+        assert!(g.completes_line(g.position(2, 0).unwrap(), Cross));
+        assert!(!g.completes_line(g.position(2, 0).unwrap(), Nought));
+        assert!(!g.completes_line(g.position(2, 2).unwrap(), Cross));
+        assert!(!g.completes_line(g.position(1, 2).unwrap(), Cross));
+    }
+
+    #[test]
+    fn test_win_condition() {
+        assert_eq!(
+            Err(GameError::InvalidSize(6)),
+            Grid::with_win_condition(5, WinCondition::full_line(6))
+        );
+        let exactly_three = WinCondition {
+            length: 3,
+            overlines: false,
+        };
+        let mut g = Grid::with_win_condition(5, exactly_three).unwrap();
+        g.set_at_pos(0, 1, Cross).unwrap();
+        g.set_at_pos(1, 1, Cross).unwrap();
+        assert_eq!(None, get_winner(&g));
+        g.set_at_pos(2, 1, Cross).unwrap();
+        assert_eq!(Some(Cross), get_winner(&g));
+        g.set_at_pos(3, 1, Cross).unwrap();
+        assert_eq!(None, get_winner(&g));
+    }
+
     #[test]
     fn test_no_winner() {
         let mut g = Grid::new(3);
@@ -266,4 +717,112 @@ mod tests {
         }
         assert!(g.is_full());
     }
+
+    #[test]
+    fn test_lines() {
+        assert_eq!(8, Grid::new(3).lines().len());
+        let cube = |side| Grid::cube(side, WinCondition::full_line(side)).unwrap();
+        assert_eq!(49, cube(3).lines().len());
+        assert_eq!(76, cube(4).lines().len());
+
+        let wc = WinCondition {
+            length: 4,
+            overlines: true,
+        };
+        let g = Grid::rectangular(7, 6, wc).unwrap();
+        assert_eq!(69, g.lines().len());
+    }
+
+    #[test]
+    fn test_cube_winner() {
+        let mut g = Grid::cube(4, WinCondition::full_line(4)).unwrap();
+        for z in 0..4 {
+            assert_eq!(None, get_winner(&g));
+            let pos = g.position_3d(3 - z, 3 - z, z).unwrap();
+            g.set(pos, Nought).unwrap();
+        }
+        assert_eq!(Some(Nought), get_winner(&g));
+        assert_eq!(Ok(None), g.get_at_pos(0, 0));
+        assert!(g.is_near_stone(g.position_3d(1, 1, 1).unwrap()));
+        assert!(!g.is_near_stone(g.position_3d(0, 3, 3).unwrap()));
+    }
+
+    #[test]
+    fn test_cube_display_shows_layers() {
+        let mut g = Grid::cube(2, WinCondition::full_line(2)).unwrap();
+        g.set(g.position_3d(1, 0, 1).unwrap(), Cross).unwrap();
+        let rendered = g.to_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!("Layer 0", lines[1]);
+        assert_eq!("Layer 1", lines[6]);
+        assert_eq!(" 0 |   | X |", lines[8]);
+    }
+
+    #[test]
+    fn test_toroidal_rows_wrap() {
+        let mut g = Grid::new(3);
+        g.set_toroidal(true);
+        assert_eq!(12, g.lines().len());
+
+        // a broken diagonal
+        g.set_at_pos(0, 1, Cross).unwrap();
+        g.set_at_pos(1, 2, Cross).unwrap();
+        assert_eq!(None, get_winner(&g));
+        g.set_at_pos(2, 0, Cross).unwrap();
+        assert_eq!(Some(Cross), get_winner(&g));
+
+        let wc = WinCondition {
+            length: 3,
+            overlines: false,
+        };
+        let mut g = Grid::with_win_condition(5, wc).unwrap();
+        g.set_toroidal(true);
+        g.set_at_pos(4, 0, Nought).unwrap();
+        g.set_at_pos(0, 0, Nought).unwrap();
+        assert!(g.completes_line(g.position(1, 0).unwrap(), Nought));
+        assert!(g.is_near_stone(g.position(4, 4).unwrap()));
+        // the whole row loops back onto itself and is one overline
+        for x in 1..4 {
+            g.set_at_pos(x, 0, Nought).unwrap();
+        }
+        assert_eq!(None, get_winner(&g));
+    }
+
+    #[test]
+    fn test_blocked_cell_breaks_rows() {
+        let mut g = Grid::new(3);
+        let center = g.position(1, 1).unwrap();
+        g.block(center).unwrap();
+        assert_eq!(BlockedCell, g.set(center, Cross).unwrap_err());
+        assert_eq!(BlockedCell, g.block(center).unwrap_err());
+        g.unset(center);
+        assert_eq!(Cell::Blocked, g.get_cell(center));
+        assert_eq!(None, g.get(center));
+
+        assert_eq!(4, g.lines().len());
+        assert_eq!(8, g.empty_cells().count());
+        assert!(!g.is_near_stone(g.position(0, 0).unwrap()));
+        g.set_at_pos(0, 0, Cross).unwrap();
+        g.set_at_pos(2, 2, Cross).unwrap();
+        assert_eq!(None, get_winner(&g));
+        assert!(!g.is_plain());
+
+        let rendered = g.to_string();
+        assert_eq!(" 1 |   | # |   |", rendered.lines().nth(3).unwrap());
+    }
+
+    #[test]
+    fn test_is_plain() {
+        assert!(Grid::new(3).is_plain());
+        let wide = Grid::rectangular(3, 5, WinCondition::full_line(3)).unwrap();
+        assert!(!wide.is_plain());
+        let short_rows = WinCondition {
+            length: 2,
+            overlines: true,
+        };
+        assert!(!Grid::with_win_condition(3, short_rows).unwrap().is_plain());
+        assert!(!Grid::cube(3, WinCondition::full_line(3))
+            .unwrap()
+            .is_plain());
+    }
 }
@@ -0,0 +1,68 @@
+use std::collections::VecDeque;
+use std::io;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+struct Lines {
+    receiver: Receiver<String>,
+    unread: VecDeque<String>,
+}
+
+/// Lines of stdin are read by a dedicated thread, so that the game can
+/// listen for commands while the AI is busy without losing any input.
+fn lines() -> &'static Mutex<Lines> {
+    static LINES: OnceLock<Mutex<Lines>> = OnceLock::new();
+    LINES.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || loop {
+            let mut line = String::new();
+            match io::stdin().read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        Mutex::new(Lines {
+            receiver,
+            unread: VecDeque::new(),
+        })
+    })
+}
+
+fn end_of_input() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "No more input")
+}
+
+pub fn read_line() -> io::Result<String> {
+    let mut lines = lines().lock().unwrap();
+    if let Some(line) = lines.unread.pop_front() {
+        return Ok(line);
+    }
+    lines.receiver.recv().map_err(|_| end_of_input())
+}
+
+/// Returns None if no line has been entered within the timeout.
+pub fn read_line_timeout(timeout: Duration) -> Option<io::Result<String>> {
+    let mut lines = lines().lock().unwrap();
+    if let Some(line) = lines.unread.pop_front() {
+        return Some(Ok(line));
+    }
+    match lines.receiver.recv_timeout(timeout) {
+        Ok(line) => Some(Ok(line)),
+        Err(RecvTimeoutError::Timeout) => None,
+        Err(RecvTimeoutError::Disconnected) => Some(Err(end_of_input())),
+    }
+}
+
+/// Puts lines back in front of the input, to be read again in the same order.
+pub fn unread_lines(read: Vec<String>) {
+    let mut lines = lines().lock().unwrap();
+    for line in read.into_iter().rev() {
+        lines.unread.push_front(line);
+    }
+}
@@ -0,0 +1,30 @@
+//! Tic-tac-toe rules and AI players. The interactive game lives in the
+//! `ttt` binary; everything here can be used by other tools as well.
+
+pub mod ai;
+pub mod book;
+pub mod error;
+pub mod game;
+pub mod gomoku;
+pub mod gravity;
+pub mod grid;
+pub mod mcts;
+pub mod multiplayer;
+pub mod notakto;
+pub mod obstacles;
+pub mod opening;
+pub mod order_chaos;
+pub mod paranoid;
+pub mod quantum;
+pub mod qubic;
+pub mod random;
+pub mod renju;
+pub mod table;
+pub mod ultimate;
+
+pub use ai::{AlphaBetaPruning, Engine, SearchStats, StopToken};
+pub use book::OpeningBook;
+pub use error::GameError;
+pub use game::{Game, GameResult, Player};
+pub use grid::{get_winner, Cell, Grid, Mark, WinCondition};
+pub use table::{PerfectPlayTable, TableEngine};
@@ -1,11 +1,122 @@
-mod ai;
 mod fsm;
-mod grid;
+mod input;
+
+use std::path::Path;
+use std::process;
+use std::sync::Arc;
+use std::time::Duration;
+use ttt::{book, table};
+
+const USAGE: &str = "Usage:
+    ttt [--stats] [--book <file>] [--table <file>]
+    ttt generate-book <side length> <plies> <seconds per position> <file>
+    ttt generate-table <side length> <file>";
 
 fn main() {
-    let mut current_state = fsm::StateType::initial_state();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("generate-book") {
+        generate_book(&args[1..]);
+        return;
+    }
+    if args.first().map(String::as_str) == Some("generate-table") {
+        generate_table(&args[1..]);
+        return;
+    }
 
-    while let Some(new_state) = current_state.to_next_state() {
+    let mut options = fsm::Options::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--stats" => options.show_stats = true,
+            "--book" => {
+                let path = args.next().unwrap_or_else(|| exit_with_usage());
+                match book::OpeningBook::load(Path::new(path)) {
+                    Ok(book) => options.book = Some(Arc::new(book)),
+                    Err(e) => {
+                        eprintln!("Failed to load opening book {}: {}", path, e);
+                        process::exit(1);
+                    }
+                }
+            }
+            "--table" => {
+                let path = args.next().unwrap_or_else(|| exit_with_usage());
+                match table::PerfectPlayTable::load(Path::new(path)) {
+                    Ok(table) => options.table = Some(Arc::new(table)),
+                    Err(e) => {
+                        eprintln!("Failed to load table {}: {}", path, e);
+                        process::exit(1);
+                    }
+                }
+            }
+            _ => exit_with_usage(),
+        }
+    }
+
+    ctrlc::set_handler(|| {
+        if !fsm::interrupt_search() {
+            process::exit(130);
+        }
+    })
+    .expect("Failed to set Ctrl-C handler");
+
+    let mut current_state = fsm::StateType::initial_state(options);
+
+    while let Some(new_state) = current_state.into_next_state() {
         current_state = new_state;
     }
 }
+
+fn generate_book(args: &[String]) {
+    let (side_length, plies, seconds, path) = match args {
+        [side_length, plies, seconds, path] => (
+            side_length.parse::<usize>(),
+            plies.parse::<usize>(),
+            seconds.parse::<u64>(),
+            Path::new(path),
+        ),
+        _ => exit_with_usage(),
+    };
+    let (side_length, plies, seconds) = match (side_length, plies, seconds) {
+        (Ok(side_length @ 2..=fsm::MAX_GRID_SIZE), Ok(plies), Ok(seconds)) => {
+            (side_length, plies, seconds)
+        }
+        _ => exit_with_usage(),
+    };
+
+    let book = book::generate(side_length, plies, Duration::from_secs(seconds));
+    if let Err(e) = book.save(path) {
+        eprintln!("Failed to save opening book {}: {}", path.display(), e);
+        process::exit(1);
+    }
+    println!("Saved {} positions to {}", book.len(), path.display());
+}
+
+fn generate_table(args: &[String]) {
+    let (side_length, path) = match args {
+        [side_length, path] => (side_length.parse::<usize>(), Path::new(path)),
+        _ => exit_with_usage(),
+    };
+    let side_length = side_length.unwrap_or_else(|_| exit_with_usage());
+
+    let table = match table::PerfectPlayTable::generate(side_length) {
+        Ok(table) => table,
+        Err(e) => {
+            eprintln!("Failed to generate table: {}", e);
+            process::exit(1);
+        }
+    };
+    if let Err(e) = table.save(path) {
+        eprintln!("Failed to save table {}: {}", path.display(), e);
+        process::exit(1);
+    }
+    println!(
+        "Saved table for {0}x{0} grid to {1}",
+        side_length,
+        path.display()
+    );
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
@@ -0,0 +1,342 @@
+//! Monte Carlo tree search for games like ultimate tic-tac-toe, whose game
+//! tree is far too large for the alpha-beta search to get anywhere.
+
+use crate::ai::{Engine, SearchStats, StopToken};
+use crate::error::GameError;
+use crate::game::{GameResult, Player};
+use crate::notakto::{NotaktoGame, NotaktoMove};
+use crate::quantum::{QuantumGame, QuantumMove};
+use crate::random::Random;
+use crate::ultimate::{UltimateGame, UltimateMove};
+use std::cmp::max;
+use std::time::Instant;
+
+/// What the search needs to know about a game to play it out.
+pub trait MonteCarloGame: Clone + Send {
+    type Move: Copy + Send;
+
+    fn get_side_to_move(&self) -> Player;
+
+    fn get_result(&self) -> Option<GameResult>;
+
+    /// The moves the side to move may make; none once the game is over.
+    fn moves(&self) -> Vec<Self::Move>;
+
+    fn make_move(&mut self, what: Player, m: Self::Move) -> Result<(), GameError>;
+}
+
+impl MonteCarloGame for UltimateGame {
+    type Move = UltimateMove;
+
+    fn get_side_to_move(&self) -> Player {
+        self.get_side_to_move()
+    }
+
+    fn get_result(&self) -> Option<GameResult> {
+        self.get_result()
+    }
+
+    fn moves(&self) -> Vec<UltimateMove> {
+        self.legal_moves().collect()
+    }
+
+    fn make_move(&mut self, what: Player, m: UltimateMove) -> Result<(), GameError> {
+        self.make_move(what.standard_mark(), m)
+    }
+}
+
+/// Random playouts per move unless set otherwise.
+pub const DEFAULT_ITERATIONS: usize = 20_000;
+
+/// Weight of the exploration term of the UCT formula.
+const EXPLORATION: f64 = 1.4;
+
+impl MonteCarloGame for QuantumGame {
+    type Move = QuantumMove;
+
+    fn get_side_to_move(&self) -> Player {
+        self.get_side_to_move()
+    }
+
+    fn get_result(&self) -> Option<GameResult> {
+        self.get_result()
+    }
+
+    fn moves(&self) -> Vec<QuantumMove> {
+        self.legal_moves()
+    }
+
+    fn make_move(&mut self, what: Player, m: QuantumMove) -> Result<(), GameError> {
+        self.make_move(what, m)
+    }
+}
+
+impl MonteCarloGame for NotaktoGame {
+    type Move = NotaktoMove;
+
+    fn get_side_to_move(&self) -> Player {
+        self.get_side_to_move()
+    }
+
+    fn get_result(&self) -> Option<GameResult> {
+        self.get_result()
+    }
+
+    fn moves(&self) -> Vec<NotaktoMove> {
+        self.legal_moves().collect()
+    }
+
+    fn make_move(&mut self, what: Player, m: NotaktoMove) -> Result<(), GameError> {
+        self.make_move(what, m)
+    }
+}
+
+struct Node<M> {
+    m: Option<M>,
+    // who made the move leading here, the scores are from their view
+    player: Player,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<M>,
+    visits: u32,
+    score: f64,
+}
+
+impl<M> Node<M> {
+    fn new<G: MonteCarloGame<Move = M>>(
+        m: Option<M>,
+        player: Player,
+        parent: Option<usize>,
+        game: &G,
+    ) -> Node<M> {
+        Node {
+            m,
+            player,
+            parent,
+            children: Vec::new(),
+            untried: game.moves(),
+            visits: 0,
+            score: 0.0,
+        }
+    }
+
+    fn uct(&self, parent_visits: u32) -> f64 {
+        let visits = f64::from(self.visits);
+        self.score / visits + EXPLORATION * (f64::from(parent_visits).ln() / visits).sqrt()
+    }
+}
+
+/// Picks moves by playing random games from the position, trying the moves
+/// that did well in them more often. Immediate wins are taken directly.
+pub struct MonteCarloEngine {
+    ai_side: Player,
+    iterations: usize,
+    random: Random,
+    stop_token: StopToken,
+}
+
+impl MonteCarloEngine {
+    pub fn new(ai_side: Player) -> MonteCarloEngine {
+        MonteCarloEngine {
+            ai_side,
+            iterations: DEFAULT_ITERATIONS,
+            random: Random::from_time(),
+            stop_token: StopToken::default(),
+        }
+    }
+
+    pub fn get_ai_side(&self) -> Player {
+        self.ai_side
+    }
+
+    /// How many random games to play per move.
+    pub fn set_iterations(&mut self, iterations: usize) {
+        self.iterations = iterations;
+    }
+
+    /// Makes the random games reproducible.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.random = Random::new(seed);
+    }
+
+    fn search<G: MonteCarloGame>(&mut self, game: &G, stats: &mut SearchStats) -> Option<G::Move> {
+        let root_player = game.get_side_to_move().opponent();
+        let mut nodes = vec![Node::new(None, root_player, None, game)];
+        for _ in 0..self.iterations {
+            if self.stop_token.is_stopped() {
+                stats.stopped = true;
+                break;
+            }
+            let mut state = game.clone();
+            let mut node = 0;
+            let mut depth = 0;
+
+            while nodes[node].untried.is_empty() && !nodes[node].children.is_empty() {
+                let parent_visits = nodes[node].visits;
+                node = *nodes[node]
+                    .children
+                    .iter()
+                    .max_by(|&&a, &&b| {
+                        let (a, b) = (nodes[a].uct(parent_visits), nodes[b].uct(parent_visits));
+                        a.total_cmp(&b)
+                    })
+                    .unwrap();
+                let m = nodes[node].m.unwrap();
+                state.make_move(nodes[node].player, m).unwrap();
+                depth += 1;
+            }
+
+            if !nodes[node].untried.is_empty() {
+                let untried = &mut nodes[node].untried;
+                let m = untried.swap_remove(self.random.below(untried.len()));
+                let player = state.get_side_to_move();
+                state.make_move(player, m).unwrap();
+                nodes.push(Node::new(Some(m), player, Some(node), &state));
+                let child = nodes.len() - 1;
+                nodes[node].children.push(child);
+                node = child;
+                depth += 1;
+            }
+            stats.nodes_visited += 1;
+            stats.max_depth = max(stats.max_depth, depth);
+
+            let result = self.play_randomly(&mut state);
+            let mut current = Some(node);
+            while let Some(ind) = current {
+                let node = &mut nodes[ind];
+                node.visits += 1;
+                node.score += match result {
+                    Some(GameResult::Win(winner)) if winner == node.player => 1.0,
+                    Some(GameResult::Draw) => 0.5,
+                    _ => 0.0,
+                };
+                current = node.parent;
+            }
+        }
+        nodes[0]
+            .children
+            .iter()
+            .max_by_key(|&&child| nodes[child].visits)
+            .and_then(|&child| nodes[child].m)
+    }
+
+    fn play_randomly<G: MonteCarloGame>(&mut self, game: &mut G) -> Option<GameResult> {
+        while game.get_result().is_none() {
+            let moves = game.moves();
+            let m = moves[self.random.below(moves.len())];
+            game.make_move(game.get_side_to_move(), m).unwrap();
+        }
+        game.get_result()
+    }
+}
+
+fn winning_move<G: MonteCarloGame>(game: &G) -> Option<G::Move> {
+    let side = game.get_side_to_move();
+    game.moves().into_iter().find(|&m| {
+        let mut next = game.clone();
+        next.make_move(side, m).is_ok() && next.get_result() == Some(GameResult::Win(side))
+    })
+}
+
+impl<G: MonteCarloGame> Engine<G> for MonteCarloEngine {
+    fn get_ai_side(&self) -> Player {
+        self.ai_side
+    }
+
+    fn get_stop_token(&self) -> StopToken {
+        self.stop_token.clone()
+    }
+
+    /// Makes the most played move of the search. If the stop token is raised
+    /// during the search, the most played move so far is made.
+    fn try_make_move(&mut self, game: &mut G) -> Result<SearchStats, GameError> {
+        if game.get_result().is_some() {
+            return Err(GameError::GameOver);
+        }
+        if game.get_side_to_move() != self.ai_side {
+            return Err(GameError::WrongSide(self.ai_side));
+        }
+
+        let start = Instant::now();
+        self.stop_token.reset();
+        let mut stats = SearchStats::default();
+        let best = match winning_move(game) {
+            Some(m) => Some(m),
+            None => self.search(game, &mut stats),
+        };
+        // a search stopped right away has no statistics to go by
+        let m = best
+            .or_else(|| game.moves().first().copied())
+            .ok_or(GameError::GameOver)?;
+        game.make_move(self.ai_side, m)?;
+        stats.elapsed = start.elapsed();
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Mark::*;
+
+    const CROSS: Player = Player::FIRST;
+    const NOUGHT: Player = Player::SECOND;
+
+    fn seeded_engine(side: Player, seed: u64) -> MonteCarloEngine {
+        let mut engine = MonteCarloEngine::new(side);
+        engine.set_seed(seed);
+        engine.set_iterations(100);
+        engine
+    }
+
+    #[test]
+    fn test_plays_on_active_board() {
+        let mut game = UltimateGame::new();
+        let m = game.position(5, 3).unwrap();
+        game.make_move(Cross, m).unwrap();
+
+        seeded_engine(NOUGHT, 1).try_make_move(&mut game).unwrap();
+        assert_eq!(None, game.get_result());
+        assert_eq!(Some(2), game.undo_move().map(|m| m.board));
+    }
+
+    #[test]
+    fn test_takes_winning_move() {
+        // a random game won by someone, before its last move
+        let mut random = seeded_engine(CROSS, 7);
+        let mut game = UltimateGame::new();
+        while let Some(GameResult::Draw) | None = random.play_randomly(&mut game) {
+            game = UltimateGame::new();
+        }
+        game.undo_move();
+
+        let side = game.get_side_to_move();
+        let mut engine = seeded_engine(side, 1);
+        engine.set_iterations(1);
+        engine.try_make_move(&mut game).unwrap();
+        assert_eq!(Some(GameResult::Win(side)), game.get_result());
+    }
+
+    #[test]
+    fn test_beats_random_play() {
+        let mut wins = 0;
+        for seed in 0..4 {
+            let mut game = UltimateGame::new();
+            let mut engine = seeded_engine(CROSS, seed);
+            let mut random = Random::new(seed);
+            while !game.is_over() {
+                if game.get_side_to_move() == CROSS {
+                    engine.try_make_move(&mut game).unwrap();
+                } else {
+                    let moves: Vec<UltimateMove> = game.legal_moves().collect();
+                    let m = moves[random.below(moves.len())];
+                    game.make_move(Nought, m).unwrap();
+                }
+            }
+            if game.get_result() == Some(GameResult::Win(CROSS)) {
+                wins += 1;
+            }
+        }
+        assert!(wins >= 3, "{} wins", wins);
+    }
+}
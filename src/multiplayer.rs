@@ -0,0 +1,105 @@
+//! Games of three or four players taking turns on one grid, such as X, O
+//! and Δ racing for four in a row on a 6x6 board.
+
+use crate::error::GameError;
+use crate::game::Game;
+use crate::grid::{Grid, Mark, WinCondition};
+
+pub const DEFAULT_PLAYER_COUNT: usize = 3;
+pub const DEFAULT_SIDE_LENGTH: usize = 6;
+pub const DEFAULT_WIN_LENGTH: usize = 4;
+
+/// How many moves the paranoid search looks ahead, a full round in games of
+/// three players and all but the last reply in games of four.
+pub const SEARCH_DEPTH: usize = 3;
+
+/// Creates a game of the first marks in turn order, one per player.
+pub fn new_game(
+    player_count: usize,
+    side_length: usize,
+    win_length: usize,
+) -> Result<Game, GameError> {
+    if player_count > Mark::ALL.len() {
+        return Err(GameError::InvalidPlayers);
+    }
+    let win_condition = WinCondition {
+        length: win_length,
+        overlines: true,
+    };
+    let grid = Grid::with_win_condition(side_length, win_condition)?;
+    Game::with_players(grid, &Mark::ALL[..player_count])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameResult, Player};
+    use crate::grid::Mark::*;
+
+    fn play(game: &mut Game, x: usize, y: usize) -> Result<(), GameError> {
+        let pos = game.get_grid().position(x, y)?;
+        game.make_move(game.get_mark_to_move(), pos)
+    }
+
+    #[test]
+    fn test_players_take_turns_in_order() {
+        let mut game = new_game(3, DEFAULT_SIDE_LENGTH, DEFAULT_WIN_LENGTH).unwrap();
+        assert_eq!(3, game.get_player_count());
+        for (i, &mark) in [Cross, Nought, Triangle, Cross].iter().enumerate() {
+            assert_eq!(Player::new(i % 3), game.get_side_to_move());
+            assert_eq!(mark, game.get_mark_to_move());
+            play(&mut game, i, 0).unwrap();
+        }
+        assert_eq!(Player::SECOND, game.get_side_to_move());
+        game.undo_move();
+        game.undo_move();
+        assert_eq!(Player::new(2), game.get_side_to_move());
+        assert_eq!(
+            Err(GameError::WrongMark(Square)),
+            game.make_move(Square, game.get_grid().position(5, 5).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_third_of_four_players_wins() {
+        let mut game = new_game(4, DEFAULT_SIDE_LENGTH, DEFAULT_WIN_LENGTH).unwrap();
+        let rounds = [
+            [(0, 0), (1, 0), (2, 5), (0, 4)],
+            [(5, 1), (4, 1), (3, 5), (5, 4)],
+            [(0, 2), (1, 2), (4, 5), (1, 5)],
+        ];
+        for round in &rounds {
+            for &(x, y) in round {
+                play(&mut game, x, y).unwrap();
+            }
+        }
+        play(&mut game, 5, 3).unwrap();
+        play(&mut game, 4, 3).unwrap();
+        assert_eq!(None, game.get_result());
+        play(&mut game, 5, 5).unwrap();
+        assert_eq!(Some(GameResult::Win(Player::new(2))), game.get_result());
+    }
+
+    #[test]
+    fn test_side_to_move_is_deduced() {
+        let mut grid = Grid::new(4);
+        grid.set_at_pos(0, 0, Cross).unwrap();
+        grid.set_at_pos(1, 0, Nought).unwrap();
+        let game = Game::with_players(grid.clone(), &[Cross, Nought, Triangle]).unwrap();
+        assert_eq!(Player::new(2), game.get_side_to_move());
+
+        grid.set_at_pos(2, 0, Square).unwrap();
+        assert_eq!(
+            Err(GameError::WrongMark(Square)),
+            Game::with_players(grid.clone(), &[Cross, Nought, Triangle]).map(|_| ())
+        );
+        assert_eq!(
+            Err(GameError::InvalidPlayers),
+            Game::with_players(grid, &[Cross, Cross]).map(|_| ())
+        );
+        assert_eq!(
+            Err(GameError::InvalidPlayers),
+            new_game(5, 6, 4).map(|_| ())
+        );
+    }
+}
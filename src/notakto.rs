@@ -0,0 +1,205 @@
+//! Notakto: both players place crosses on a row of 3x3 boards. A board
+//! with a complete line is dead, and whoever kills the last live board
+//! loses.
+
+use crate::error::GameError;
+use crate::game::{GameResult, Player};
+use crate::grid::{get_winner, Grid, Mark, Position};
+use std::fmt;
+
+pub mod quotient;
+
+pub use quotient::NotaktoEngine;
+
+pub const SIDE_LENGTH: usize = 3;
+
+pub const DEFAULT_BOARD_COUNT: usize = 3;
+
+/// A cell of one of the boards, which are numbered from the left.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct NotaktoMove {
+    pub board: usize,
+    pub pos: Position,
+}
+
+/// Both players place crosses, the first player moving first.
+#[derive(Clone, Debug)]
+pub struct NotaktoGame {
+    boards: Vec<Grid>,
+    side_to_move: Player,
+    result: Option<GameResult>,
+    history: Vec<NotaktoMove>,
+}
+
+impl NotaktoGame {
+    pub fn new(board_count: usize) -> Result<NotaktoGame, GameError> {
+        if board_count == 0 {
+            return Err(GameError::InvalidSize(board_count));
+        }
+        Ok(NotaktoGame {
+            boards: vec![Grid::new(SIDE_LENGTH); board_count],
+            side_to_move: Player::FIRST,
+            result: None,
+            history: Vec::new(),
+        })
+    }
+
+    pub fn get_boards(&self) -> &[Grid] {
+        &self.boards
+    }
+
+    pub fn get_side_to_move(&self) -> Player {
+        self.side_to_move
+    }
+
+    pub fn get_result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// Whether the board has a complete line, so that nobody can play on it.
+    pub fn is_dead(&self, board: usize) -> bool {
+        get_winner(&self.boards[board]).is_some()
+    }
+
+    /// The move at the given column and row of the board.
+    pub fn position(&self, board: usize, x: usize, y: usize) -> Result<NotaktoMove, GameError> {
+        let grid = self.boards.get(board).ok_or(GameError::OutOfBounds)?;
+        Ok(NotaktoMove {
+            board,
+            pos: grid.position(x, y)?,
+        })
+    }
+
+    /// Iterates over the empty cells of the live boards.
+    pub fn legal_moves(&self) -> impl Iterator<Item = NotaktoMove> + '_ {
+        (0..self.boards.len())
+            .filter(move |&board| !self.is_over() && !self.is_dead(board))
+            .flat_map(move |board| {
+                self.boards[board]
+                    .empty_cells()
+                    .map(move |pos| NotaktoMove { board, pos })
+            })
+    }
+
+    pub fn make_move(&mut self, what: Player, m: NotaktoMove) -> Result<(), GameError> {
+        if self.is_over() {
+            return Err(GameError::GameOver);
+        }
+        if what != self.side_to_move {
+            return Err(GameError::WrongSide(what));
+        }
+        if m.board >= self.boards.len() {
+            return Err(GameError::OutOfBounds);
+        }
+        if self.is_dead(m.board) {
+            return Err(GameError::InactiveBoard);
+        }
+        self.boards[m.board].set(m.pos, Mark::Cross)?;
+        self.history.push(m);
+        self.side_to_move = what.opponent();
+        if (0..self.boards.len()).all(|board| self.is_dead(board)) {
+            self.result = Some(GameResult::Win(self.side_to_move));
+        }
+        Ok(())
+    }
+
+    /// Takes back the last move, returns it.
+    pub fn undo_move(&mut self) -> Option<NotaktoMove> {
+        let m = self.history.pop()?;
+        self.boards[m.board].unset(m.pos);
+        self.side_to_move = self.side_to_move.opponent();
+        self.result = None;
+        Some(m)
+    }
+}
+
+/// Renders the boards side by side, marking the dead ones.
+impl fmt::Display for NotaktoGame {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let width = 4 * SIDE_LENGTH + 1;
+        write!(formatter, "\n   ")?;
+        for board in 0..self.boards.len() {
+            let title = if self.is_dead(board) {
+                format!("Board {} (dead)", board)
+            } else {
+                format!("Board {}", board)
+            };
+            write!(formatter, "{:^width$}   ", title, width = width)?;
+        }
+        write!(formatter, "\n   ")?;
+        for _ in &self.boards {
+            write!(formatter, " ")?;
+            for col in 0..SIDE_LENGTH {
+                write!(formatter, "{:^3} ", col)?;
+            }
+            write!(formatter, "   ")?;
+        }
+        for row in 0..SIDE_LENGTH {
+            write!(formatter, "\n{:^3}", row)?;
+            for grid in &self.boards {
+                write!(formatter, "|")?;
+                for col in 0..SIDE_LENGTH {
+                    let pos = grid.position(col, row).map_err(|_| fmt::Error)?;
+                    let symbol = grid.get(pos).map_or(' ', Mark::symbol);
+                    write!(formatter, "{:^3}|", symbol)?;
+                }
+                write!(formatter, "   ")?;
+            }
+        }
+        writeln!(formatter)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(game: &mut NotaktoGame, board: usize, x: usize, y: usize) -> Result<(), GameError> {
+        let m = game.position(board, x, y)?;
+        game.make_move(game.get_side_to_move(), m)
+    }
+
+    #[test]
+    fn test_killing_last_board_loses() {
+        let mut game = NotaktoGame::new(2).unwrap();
+        assert_eq!(18, game.legal_moves().count());
+        for &(x, y) in &[(0, 0), (1, 0), (2, 0)] {
+            play(&mut game, 0, x, y).unwrap();
+        }
+        assert!(game.is_dead(0));
+        assert_eq!(None, game.get_result());
+        assert_eq!(9, game.legal_moves().count());
+        assert_eq!(Err(GameError::InactiveBoard), play(&mut game, 0, 1, 1));
+
+        // Nought kills the second board as well
+        for &(x, y) in &[(0, 0), (1, 1), (2, 2)] {
+            play(&mut game, 1, x, y).unwrap();
+        }
+        assert_eq!(Some(GameResult::Win(Player::FIRST)), game.get_result());
+        assert_eq!(0, game.legal_moves().count());
+
+        game.undo_move();
+        assert_eq!(None, game.get_result());
+        assert_eq!(Player::SECOND, game.get_side_to_move());
+        assert!(!game.is_dead(1));
+    }
+
+    #[test]
+    fn test_render_boards_side_by_side() {
+        let mut game = NotaktoGame::new(2).unwrap();
+        for &x in &[0, 1, 2] {
+            play(&mut game, 0, x, 1).unwrap();
+        }
+        play(&mut game, 1, 2, 2).unwrap();
+        let rendered = game.to_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!("   Board 0 (dead)      Board 1      ", lines[1]);
+        assert_eq!(" 1 | X | X | X |   |   |   |   |   ", lines[4]);
+        assert_eq!(" 2 |   |   |   |   |   |   | X |   ", lines[5]);
+    }
+}
@@ -0,0 +1,322 @@
+//! Perfect play of Notakto from Plambeck's misère quotient. Every live
+//! board has a value in an 18 element commutative monoid, and the player to
+//! move loses exactly when the product of the values is one of the four
+//! P-positions a, b², bc and c².
+
+use super::{NotaktoGame, NotaktoMove};
+use crate::ai::{Engine, SearchStats, StopToken};
+use crate::book::canonical_form;
+use crate::error::GameError;
+use crate::game::Player;
+use crate::grid::{get_winner, Grid};
+use std::time::Instant;
+
+/// An element of the quotient, a^a b^b c^c d^d in normal form.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct QuotientValue {
+    a: u8,
+    b: u8,
+    c: u8,
+    d: u8,
+}
+
+impl QuotientValue {
+    pub const ONE: QuotientValue = QuotientValue::new(0, 0, 0, 0);
+    pub const A: QuotientValue = QuotientValue::new(1, 0, 0, 0);
+    pub const B: QuotientValue = QuotientValue::new(0, 1, 0, 0);
+    pub const AB: QuotientValue = QuotientValue::new(1, 1, 0, 0);
+    pub const C: QuotientValue = QuotientValue::new(0, 0, 1, 0);
+    pub const C2: QuotientValue = QuotientValue::new(0, 0, 2, 0);
+    pub const D: QuotientValue = QuotientValue::new(0, 0, 0, 1);
+    pub const AD: QuotientValue = QuotientValue::new(1, 0, 0, 1);
+
+    const fn new(a: u8, b: u8, c: u8, d: u8) -> QuotientValue {
+        QuotientValue { a, b, c, d }
+    }
+
+    /// Multiplies the values, reducing the product with the relations
+    /// a² = 1, b³ = b, b²c = c, c³ = ac², b²d = d, cd = ad and d² = c².
+    pub fn times(self, other: QuotientValue) -> QuotientValue {
+        let (mut a, mut b, mut c, mut d) = (
+            self.a + other.a,
+            self.b + other.b,
+            self.c + other.c,
+            self.d + other.d,
+        );
+        a %= 2;
+        while d >= 2 {
+            d -= 2;
+            c += 2;
+        }
+        if d == 1 {
+            a ^= c % 2;
+            c = 0;
+        }
+        if c >= 2 {
+            a ^= (c - 2) % 2;
+            c = 2;
+        }
+        if b >= 3 {
+            b = 1 + (b - 1) % 2;
+        }
+        if (c >= 1 || d == 1) && b == 2 {
+            b = 0;
+        }
+        QuotientValue::new(a, b, c, d)
+    }
+
+    /// Whether the player to move loses with this value.
+    pub fn is_p_position(self) -> bool {
+        let p_positions = [
+            QuotientValue::A,
+            QuotientValue::B.times(QuotientValue::B),
+            QuotientValue::B.times(QuotientValue::C),
+            QuotientValue::C2,
+        ];
+        p_positions.contains(&self)
+    }
+}
+
+/// Values of the live boards up to symmetry, keyed by the canonical form of
+/// the book.
+const BOARD_VALUES: [(&str, QuotientValue); 46] = [
+    (".........", QuotientValue::C),
+    ("....X....", QuotientValue::C2),
+    (".......X.", QuotientValue::ONE),
+    ("........X", QuotientValue::ONE),
+    ("...X.X...", QuotientValue::A),
+    ("..X...X..", QuotientValue::A),
+    (".....XX..", QuotientValue::B),
+    ("....X..X.", QuotientValue::B),
+    (".....X.X.", QuotientValue::A),
+    ("....X...X", QuotientValue::B),
+    ("......X.X", QuotientValue::B),
+    (".......XX", QuotientValue::D),
+    ("....XXX..", QuotientValue::A),
+    ("..XX...X.", QuotientValue::ONE),
+    ("...X.X.X.", QuotientValue::B),
+    ("....XX.X.", QuotientValue::AB),
+    ("..X...XX.", QuotientValue::AD),
+    (".....XXX.", QuotientValue::AD),
+    ("..XX....X", QuotientValue::A),
+    ("...X.X..X", QuotientValue::AD),
+    ("..X...X.X", QuotientValue::AB),
+    ("....X.X.X", QuotientValue::A),
+    (".....XX.X", QuotientValue::A),
+    ("....X..XX", QuotientValue::AB),
+    (".....X.XX", QuotientValue::B),
+    ("..XX.XX..", QuotientValue::A),
+    ("..XXX..X.", QuotientValue::B),
+    (".X.X.X.X.", QuotientValue::A),
+    ("..XX.X.X.", QuotientValue::AB),
+    ("..XX..XX.", QuotientValue::A),
+    ("..X..XXX.", QuotientValue::AB),
+    ("....XXXX.", QuotientValue::B),
+    ("..XXX...X", QuotientValue::B),
+    ("X.X...X.X", QuotientValue::A),
+    ("..XX..X.X", QuotientValue::B),
+    ("...X.XX.X", QuotientValue::B),
+    ("....XXX.X", QuotientValue::B),
+    ("..XX...XX", QuotientValue::B),
+    ("...X.X.XX", QuotientValue::A),
+    ("....XX.XX", QuotientValue::A),
+    ("..XX.XXX.", QuotientValue::B),
+    (".XXX..X.X", QuotientValue::A),
+    (".X.X.XX.X", QuotientValue::A),
+    ("..XXX..XX", QuotientValue::A),
+    (".X.X.X.XX", QuotientValue::B),
+    (".XXX.XXX.", QuotientValue::A),
+];
+
+/// Value of a single board, one for a dead board.
+pub fn board_value(grid: &Grid) -> QuotientValue {
+    if get_winner(grid).is_some() {
+        return QuotientValue::ONE;
+    }
+    let (key, _) = canonical_form(grid);
+    BOARD_VALUES
+        .iter()
+        .find(|(form, _)| *form == key)
+        .map(|&(_, value)| value)
+        .expect("every live board is in the table")
+}
+
+/// Value of the whole position, the product of the values of its boards.
+pub fn position_value(game: &NotaktoGame) -> QuotientValue {
+    game.get_boards()
+        .iter()
+        .map(board_value)
+        .fold(QuotientValue::ONE, QuotientValue::times)
+}
+
+/// Plays Notakto perfectly without searching, by moving to a position whose
+/// value is a P-position whenever there is one.
+pub struct NotaktoEngine {
+    ai_side: Player,
+    stop_token: StopToken,
+}
+
+impl NotaktoEngine {
+    pub fn new(ai_side: Player) -> NotaktoEngine {
+        NotaktoEngine {
+            ai_side,
+            stop_token: StopToken::default(),
+        }
+    }
+}
+
+impl Engine<NotaktoGame> for NotaktoEngine {
+    fn get_ai_side(&self) -> Player {
+        self.ai_side
+    }
+
+    fn get_stop_token(&self) -> StopToken {
+        self.stop_token.clone()
+    }
+
+    /// Makes a winning move if the position has one. Otherwise a move that
+    /// doesn't kill the last board is made, to give the opponent a chance to
+    /// go wrong.
+    fn try_make_move(&mut self, game: &mut NotaktoGame) -> Result<SearchStats, GameError> {
+        if game.is_over() {
+            return Err(GameError::GameOver);
+        }
+        if game.get_side_to_move() != self.ai_side {
+            return Err(GameError::WrongSide(self.ai_side));
+        }
+
+        let start = Instant::now();
+        let mut stats = SearchStats::default();
+        let moves: Vec<NotaktoMove> = game.legal_moves().collect();
+        let mut fallback = None;
+        let mut best = None;
+        for &m in &moves {
+            stats.nodes_visited += 1;
+            game.make_move(self.ai_side, m)?;
+            let over = game.is_over();
+            let winning = !over && position_value(game).is_p_position();
+            game.undo_move();
+            if winning {
+                best = Some(m);
+                break;
+            }
+            if !over && fallback.is_none() {
+                fallback = Some(m);
+            }
+        }
+        let m = best
+            .or(fallback)
+            .or_else(|| moves.first().copied())
+            .ok_or(GameError::GameOver)?;
+        game.make_move(self.ai_side, m)?;
+        stats.max_depth = 1;
+        stats.elapsed = start.elapsed();
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameResult;
+    use std::collections::{HashMap, HashSet};
+
+    /// The live boards up to symmetry and order, which is all that matters.
+    fn live_boards(game: &NotaktoGame) -> Vec<String> {
+        let mut boards: Vec<String> = game
+            .get_boards()
+            .iter()
+            .filter(|grid| get_winner(grid).is_none())
+            .map(|grid| canonical_form(grid).0)
+            .collect();
+        boards.sort();
+        boards
+    }
+
+    /// Whether the player to move wins, found by searching the whole game.
+    fn wins_by_search(game: &mut NotaktoGame, cache: &mut HashMap<Vec<String>, bool>) -> bool {
+        if game.is_over() {
+            return true;
+        }
+        let key = live_boards(game);
+        if let Some(&wins) = cache.get(&key) {
+            return wins;
+        }
+        let moves: Vec<NotaktoMove> = game.legal_moves().collect();
+        let side = game.get_side_to_move();
+        let mut wins = false;
+        for m in moves {
+            game.make_move(side, m).unwrap();
+            wins = !wins_by_search(game, cache);
+            game.undo_move();
+            if wins {
+                break;
+            }
+        }
+        cache.insert(key, wins);
+        wins
+    }
+
+    fn check_all_positions(
+        game: &mut NotaktoGame,
+        checked: &mut HashSet<Vec<String>>,
+        cache: &mut HashMap<Vec<String>, bool>,
+    ) {
+        if game.is_over() || !checked.insert(live_boards(game)) {
+            return;
+        }
+        assert_eq!(
+            !wins_by_search(game, cache),
+            position_value(game).is_p_position(),
+            "{}",
+            game
+        );
+        let moves: Vec<NotaktoMove> = game.legal_moves().collect();
+        let side = game.get_side_to_move();
+        for m in moves {
+            game.make_move(side, m).unwrap();
+            check_all_positions(game, checked, cache);
+            game.undo_move();
+        }
+    }
+
+    #[test]
+    fn test_quotient_agrees_with_search() {
+        let mut checked = HashSet::new();
+        let mut cache = HashMap::new();
+        let mut game = NotaktoGame::new(2).unwrap();
+        check_all_positions(&mut game, &mut checked, &mut cache);
+        // pairs of live boards, single live boards and the empty position
+        assert_eq!(46 * 47 / 2 + 46, checked.len());
+    }
+
+    #[test]
+    fn test_relations() {
+        let b2 = QuotientValue::B.times(QuotientValue::B);
+        assert_eq!(QuotientValue::ONE, QuotientValue::A.times(QuotientValue::A));
+        assert_eq!(QuotientValue::B, b2.times(QuotientValue::B));
+        assert_eq!(QuotientValue::C, b2.times(QuotientValue::C));
+        assert_eq!(QuotientValue::C2, QuotientValue::D.times(QuotientValue::D));
+        assert_eq!(QuotientValue::AD, QuotientValue::C.times(QuotientValue::D));
+        assert!(!QuotientValue::C.is_p_position());
+        assert!(QuotientValue::C2.is_p_position());
+    }
+
+    #[test]
+    fn test_engine_wins_from_the_right_seat() {
+        for &(board_count, winner) in &[(1, Player::FIRST), (2, Player::SECOND), (3, Player::FIRST)]
+        {
+            let mut game = NotaktoGame::new(board_count).unwrap();
+            let mut winning = NotaktoEngine::new(winner);
+            let mut losing = NotaktoEngine::new(winner.opponent());
+            while !game.is_over() {
+                if game.get_side_to_move() == winner {
+                    winning.try_make_move(&mut game).unwrap();
+                } else {
+                    losing.try_make_move(&mut game).unwrap();
+                }
+            }
+            assert_eq!(Some(GameResult::Win(winner)), game.get_result());
+        }
+    }
+}
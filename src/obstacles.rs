@@ -0,0 +1,69 @@
+//! Obstacles: squares blocked before the game starts, which nobody can play
+//! and which break the rows running through them.
+
+use crate::error::GameError;
+use crate::grid::Grid;
+use crate::random::Random;
+
+/// Blocks the squares at the given columns and rows.
+pub fn block_cells(grid: &mut Grid, cells: &[(usize, usize)]) -> Result<(), GameError> {
+    for &(x, y) in cells {
+        grid.block(grid.position(x, y)?)?;
+    }
+    Ok(())
+}
+
+/// Blocks the given number of empty squares, picked at random. Fails if the
+/// grid doesn't have that many empty squares.
+pub fn block_random_cells(
+    grid: &mut Grid,
+    count: usize,
+    random: &mut Random,
+) -> Result<(), GameError> {
+    let mut empty: Vec<_> = grid.empty_cells().collect();
+    if count > empty.len() {
+        return Err(GameError::TooManyObstacles(count));
+    }
+    for _ in 0..count {
+        let pos = empty.swap_remove(random.below(empty.len()));
+        grid.block(pos)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Cell;
+
+    #[test]
+    fn test_block_cells() {
+        let mut grid = Grid::new(3);
+        block_cells(&mut grid, &[(0, 0), (2, 1)]).unwrap();
+        assert_eq!(2, grid.blocked_cells().count());
+        assert_eq!(Cell::Blocked, grid.get_cell(grid.position(2, 1).unwrap()));
+        assert_eq!(
+            Err(GameError::BlockedCell),
+            block_cells(&mut grid, &[(0, 0)])
+        );
+        assert_eq!(
+            Err(GameError::OutOfBounds),
+            block_cells(&mut grid, &[(3, 0)])
+        );
+    }
+
+    #[test]
+    fn test_block_random_cells() {
+        let mut grid = Grid::new(4);
+        let mut random = Random::new(3);
+        block_random_cells(&mut grid, 5, &mut random).unwrap();
+        assert_eq!(5, grid.blocked_cells().count());
+        assert_eq!(11, grid.empty_cells().count());
+
+        assert_eq!(
+            Err(GameError::TooManyObstacles(12)),
+            block_random_cells(&mut grid, 12, &mut random)
+        );
+        assert_eq!(5, grid.blocked_cells().count());
+    }
+}
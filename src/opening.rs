@@ -0,0 +1,221 @@
+//! Opening rules that make up for the advantage of moving first in Gomoku.
+//!
+//! Pro and Long Pro restrict where Cross may place its first two marks. In
+//! Swap the opener places two crosses and a nought, then the other player
+//! picks a side. Swap2 also lets that player place one more mark of each
+//! side instead, in which case the opener picks the side.
+
+use crate::error::GameError;
+use crate::game::{Game, Player};
+use crate::grid::{Grid, Mark, Position};
+use std::cmp::max;
+use std::convert::TryInto;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Protocol {
+    Pro,
+    LongPro,
+    Swap,
+    Swap2,
+}
+
+/// Marks the opener places in Swap and Swap2 before the side choice.
+pub const OPENING_MARKS: usize = 3;
+
+/// Marks placed in Swap2 by the player who postpones the side choice.
+pub const EXTRA_MARKS: usize = 2;
+
+/// Offsets from the center where the AI places the opening marks, Cross
+/// and Nought by turns. The shape gives neither side a clear edge.
+const AI_OPENING: [(isize, isize); OPENING_MARKS + EXTRA_MARKS] =
+    [(0, 0), (1, -1), (1, 1), (2, 0), (-1, 1)];
+
+/// How far apart the scores of the sides have to be for the AI to consider
+/// one side clearly better.
+const CLEAR_ADVANTAGE: i64 = 32;
+
+/// What the player deciding the sides in Swap and Swap2 does.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SideChoice {
+    Take(Player),
+    PlaceMore,
+}
+
+impl Protocol {
+    /// Minimum distance from the center of the second cross, which is the
+    /// third mark of the game, for the protocols restricting placements.
+    pub fn min_third_move_distance(self) -> Option<usize> {
+        match self {
+            Protocol::Pro => Some(3),
+            Protocol::LongPro => Some(4),
+            Protocol::Swap | Protocol::Swap2 => None,
+        }
+    }
+
+    /// Whether the protocol ends with a player choosing sides.
+    pub fn has_side_choice(self) -> bool {
+        matches!(self, Protocol::Swap | Protocol::Swap2)
+    }
+
+    /// Prepares the game for the protocol, fails if the board is too small.
+    pub fn apply(self, game: &mut Game) -> Result<(), GameError> {
+        let side_length = game.get_grid().get_side_length();
+        if let Some(distance) = self.min_third_move_distance() {
+            if side_length <= 2 * distance {
+                return Err(GameError::InvalidSize(side_length));
+            }
+        }
+        game.set_min_third_move_distance(self.min_third_move_distance());
+        Ok(())
+    }
+}
+
+/// Distance of the position from the center of the grid, counted in moves
+/// of a chess king.
+pub fn distance_from_center(grid: &Grid, pos: Position) -> usize {
+    let center = grid.get_side_length() / 2;
+    max(pos.x().abs_diff(center), pos.y().abs_diff(center))
+}
+
+/// Where the AI places the next opening mark for the side to move. Falls
+/// back to the first legal move when the prepared shape is taken.
+pub fn ai_opening_mark(game: &Game) -> Option<Position> {
+    let grid = game.get_grid();
+    let center = (grid.get_side_length() / 2) as isize;
+    AI_OPENING
+        .get(game.get_move_count())
+        .and_then(|&(dx, dy)| {
+            let (x, y) = (center + dx, center + dy);
+            grid.position(x.try_into().ok()?, y.try_into().ok()?).ok()
+        })
+        .filter(|&pos| grid.is_empty_cell(pos) && !game.is_forbidden(pos))
+        .or_else(|| game.legal_moves().next())
+}
+
+/// How the AI decides the sides. Postpones the decision when it is allowed
+/// to and neither side is clearly better, otherwise takes the better side,
+/// Nought on a close call since it moves next.
+pub fn ai_side_choice(game: &Game, may_place_more: bool) -> SideChoice {
+    let score = balance(game);
+    if score > CLEAR_ADVANTAGE {
+        SideChoice::Take(Player::FIRST)
+    } else if score < -CLEAR_ADVANTAGE || !may_place_more {
+        SideChoice::Take(Player::SECOND)
+    } else {
+        SideChoice::PlaceMore
+    }
+}
+
+/// Evaluation after the best reply of the side to move, so that having the
+/// move counts as well.
+fn balance(game: &Game) -> i64 {
+    let side = game.get_side_to_move();
+    let scores = game.legal_moves().map(|pos| {
+        let mut next = game.clone();
+        next.make_move(game.get_mark(side), pos).unwrap();
+        evaluate(next.get_grid())
+    });
+    let best = if side == Player::FIRST {
+        scores.max()
+    } else {
+        scores.min()
+    };
+    best.unwrap_or_else(|| evaluate(game.get_grid()))
+}
+
+/// Rough measure of how promising the grid is for Cross, negative when it
+/// favours Nought. Every window of winning length containing marks of one
+/// side only counts for that side, four times more with each extra mark.
+pub fn evaluate(grid: &Grid) -> i64 {
+    let n = grid.get_side_length() as isize;
+    let length = grid.get_win_condition().length as isize;
+    let mut score = 0;
+    for &(dx, dy) in &[(1, 0), (0, 1), (1, 1), (1, -1)] {
+        for y in 0..n {
+            for x in 0..n {
+                let (end_x, end_y) = (x + dx * (length - 1), y + dy * (length - 1));
+                if !(0..n).contains(&end_x) || !(0..n).contains(&end_y) {
+                    continue;
+                }
+                let (mut crosses, mut noughts) = (0, 0);
+                for k in 0..length {
+                    let ind = ((y + dy * k) * n + x + dx * k) as usize;
+                    match grid.get_at_ind(ind) {
+                        Ok(Some(Mark::Cross)) => crosses += 1,
+                        Ok(Some(Mark::Nought)) => noughts += 1,
+                        _ => {}
+                    }
+                }
+                match (crosses, noughts) {
+                    (0, 0) => {}
+                    (c, 0) => score += 1 << (2 * c),
+                    (0, o) => score -= 1 << (2 * o),
+                    _ => {}
+                }
+            }
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gomoku;
+
+    fn play(game: &mut Game, x: usize, y: usize) -> Result<(), GameError> {
+        let pos = game.get_grid().position(x, y)?;
+        game.make_move(game.get_mark_to_move(), pos)
+    }
+
+    #[test]
+    fn test_pro_restricts_cross_placements() {
+        let mut game = gomoku::new_game(15, gomoku::Rules::Freestyle).unwrap();
+        Protocol::Pro.apply(&mut game).unwrap();
+
+        assert_eq!(Err(GameError::ForbiddenMove), play(&mut game, 6, 6));
+        assert_eq!(1, game.legal_moves().count());
+        play(&mut game, 7, 7).unwrap();
+        play(&mut game, 6, 6).unwrap();
+        assert_eq!(Err(GameError::ForbiddenMove), play(&mut game, 9, 5));
+        assert!(game
+            .legal_moves()
+            .all(|pos| distance_from_center(game.get_grid(), pos) >= 3));
+        play(&mut game, 10, 5).unwrap();
+        play(&mut game, 8, 8).unwrap();
+    }
+
+    #[test]
+    fn test_long_pro_needs_large_board() {
+        let mut game = gomoku::new_game(8, gomoku::Rules::Freestyle).unwrap();
+        assert_eq!(
+            Err(GameError::InvalidSize(8)),
+            Protocol::LongPro.apply(&mut game)
+        );
+        let mut game = gomoku::new_game(9, gomoku::Rules::Freestyle).unwrap();
+        Protocol::LongPro.apply(&mut game).unwrap();
+        Protocol::Swap2.apply(&mut game).unwrap();
+        assert_eq!(81, game.legal_moves().count());
+    }
+
+    #[test]
+    fn test_ai_opening_is_balanced() {
+        let mut game = gomoku::new_game(15, gomoku::Rules::Freestyle).unwrap();
+        for _ in 0..OPENING_MARKS {
+            let pos = ai_opening_mark(&game).unwrap();
+            game.make_move(game.get_mark_to_move(), pos).unwrap();
+        }
+        assert_eq!(SideChoice::PlaceMore, ai_side_choice(&game, true));
+        assert!(matches!(ai_side_choice(&game, false), SideChoice::Take(_)));
+    }
+
+    #[test]
+    fn test_ai_takes_clearly_better_side() {
+        let mut game = gomoku::new_game(15, gomoku::Rules::Freestyle).unwrap();
+        for &(x, y) in &[(7, 7), (0, 0), (8, 7)] {
+            play(&mut game, x, y).unwrap();
+        }
+        assert!(evaluate(game.get_grid()) > 0);
+        assert_eq!(SideChoice::Take(Player::FIRST), ai_side_choice(&game, true));
+    }
+}
@@ -0,0 +1,100 @@
+//! Order and Chaos: both sides place crosses or noughts as they like on a
+//! 6x6 board. Order wins with exactly five of either mark in a row, Chaos by
+//! filling the board without one.
+
+use crate::error::GameError;
+use crate::game::{Game, Player};
+use crate::grid::{Grid, WinCondition};
+
+/// Order moves first, whichever marks either side places.
+pub const ORDER: Player = Player::FIRST;
+pub const CHAOS: Player = Player::SECOND;
+
+pub const SIDE_LENGTH: usize = 6;
+pub const WIN_LENGTH: usize = 5;
+
+/// How deep the AI searches, with both marks to try at every square.
+pub const SEARCH_DEPTH: usize = 3;
+
+/// The name of the role the player takes.
+pub fn role_name(player: Player) -> &'static str {
+    if player == ORDER {
+        "Order"
+    } else {
+        "Chaos"
+    }
+}
+
+pub fn new_game() -> Result<Game, GameError> {
+    let win_condition = WinCondition {
+        length: WIN_LENGTH,
+        overlines: false,
+    };
+    let mut game = Game::from_grid(Grid::with_win_condition(SIDE_LENGTH, win_condition)?)?;
+    game.set_order_and_chaos(true);
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameResult;
+    use crate::grid::Mark::{self, *};
+
+    fn play(game: &mut Game, what: Mark, x: usize, y: usize) -> Result<(), GameError> {
+        let pos = game.get_grid().position(x, y)?;
+        game.make_move(what, pos)
+    }
+
+    #[test]
+    fn test_roles_are_players_not_marks() {
+        let mut game = new_game().unwrap();
+        assert_eq!(ORDER, game.get_side_to_move());
+        play(&mut game, Nought, 0, 0).unwrap();
+        assert_eq!(CHAOS, game.get_side_to_move());
+        play(&mut game, Nought, 1, 0).unwrap();
+        assert_eq!(ORDER, game.get_side_to_move());
+        assert_eq!("Order", role_name(ORDER));
+        assert_eq!("Chaos", role_name(CHAOS));
+    }
+
+    #[test]
+    fn test_any_line_wins_for_order() {
+        let mut game = new_game().unwrap();
+        play(&mut game, Cross, 5, 5).unwrap();
+        let marks: Vec<Mark> = game.placeable_marks().collect();
+        assert_eq!(vec![Nought, Cross], marks);
+
+        // a line counts for Order even when Chaos completes it
+        for y in 0..4 {
+            play(&mut game, Nought, 0, y).unwrap();
+        }
+        assert_eq!(CHAOS, game.get_side_to_move());
+        play(&mut game, Nought, 0, 4).unwrap();
+        assert_eq!(Some(GameResult::Win(ORDER)), game.get_result());
+    }
+
+    #[test]
+    fn test_six_in_a_row_doesnt_count() {
+        let mut game = new_game().unwrap();
+        for &x in &[0, 1, 2, 3, 5] {
+            play(&mut game, Cross, x, 0).unwrap();
+        }
+        play(&mut game, Cross, 4, 0).unwrap();
+        assert_eq!(None, game.get_result());
+    }
+
+    #[test]
+    fn test_full_board_wins_for_chaos() {
+        let mut game = new_game().unwrap();
+        // pairs of each mark in every row, column and diagonal
+        for y in 0..SIDE_LENGTH {
+            for x in 0..SIDE_LENGTH {
+                assert_eq!(None, game.get_result());
+                let what = if (x + 2 * y) % 4 < 2 { Cross } else { Nought };
+                play(&mut game, what, x, y).unwrap();
+            }
+        }
+        assert_eq!(Some(GameResult::Win(CHAOS)), game.get_result());
+    }
+}
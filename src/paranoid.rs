@@ -0,0 +1,190 @@
+//! Paranoid search for games of more than two players: the AI assumes that
+//! all the other players team up against it, which turns the game back into
+//! a two-sided one that alpha-beta pruning can search.
+
+use crate::ai::{Engine, SearchStats, StopToken};
+use crate::error::GameError;
+use crate::game::{Game, GameResult, Player};
+use crate::grid::Position;
+use std::cmp::{max, min};
+use std::time::Instant;
+
+/// Score of a won game, less the number of moves it takes to get there.
+/// Above what any evaluation of the lines comes to.
+const WIN_SCORE: i64 = 1 << 40;
+
+pub struct ParanoidSearch {
+    ai_side: Player,
+    depth_limit: usize,
+    stop_token: StopToken,
+}
+
+impl ParanoidSearch {
+    /// Creates an engine looking the given number of moves ahead.
+    pub fn new(ai_side: Player, depth_limit: usize) -> ParanoidSearch {
+        ParanoidSearch {
+            ai_side,
+            depth_limit,
+            stop_token: StopToken::default(),
+        }
+    }
+
+    fn search(
+        &self,
+        game: &mut Game,
+        lines: &[Vec<Position>],
+        depth: usize,
+        mut alpha: i64,
+        mut beta: i64,
+        stats: &mut SearchStats,
+    ) -> i64 {
+        stats.nodes_visited += 1;
+        stats.max_depth = max(stats.max_depth, depth);
+        match game.get_result() {
+            Some(GameResult::Win(winner)) if winner == self.ai_side => {
+                return WIN_SCORE - depth as i64
+            }
+            Some(GameResult::Win(_)) => return depth as i64 - WIN_SCORE,
+            Some(GameResult::Draw) => return 0,
+            None => {}
+        }
+        if depth == self.depth_limit || self.stop_token.is_stopped() {
+            return game.evaluate(self.ai_side, lines);
+        }
+
+        let side = game.get_side_to_move();
+        let maximizing = side == self.ai_side;
+        let moves: Vec<Position> = game.legal_moves().collect();
+        for pos in moves {
+            game.make_move(game.get_mark(side), pos).unwrap();
+            let score = self.search(game, lines, depth + 1, alpha, beta, stats);
+            game.undo_move();
+            if maximizing {
+                alpha = max(alpha, score);
+            } else {
+                beta = min(beta, score);
+            }
+            if alpha >= beta {
+                stats.cutoffs += 1;
+                break;
+            }
+        }
+        if maximizing {
+            alpha
+        } else {
+            beta
+        }
+    }
+}
+
+impl Engine for ParanoidSearch {
+    fn get_ai_side(&self) -> Player {
+        self.ai_side
+    }
+
+    fn get_stop_token(&self) -> StopToken {
+        self.stop_token.clone()
+    }
+
+    /// Makes the move that does best against all other players at once. If
+    /// the stop token is raised, the best move found so far is made.
+    fn try_make_move(&mut self, game: &mut Game) -> Result<SearchStats, GameError> {
+        if game.is_over() {
+            return Err(GameError::GameOver);
+        }
+        if game.get_side_to_move() != self.ai_side {
+            return Err(GameError::WrongSide(self.ai_side));
+        }
+
+        let start = Instant::now();
+        self.stop_token.reset();
+        let mut stats = SearchStats::default();
+        let lines = game.get_grid().lines();
+        let mut searched = game.clone();
+        let moves: Vec<Position> = game.legal_moves().collect();
+        let mut best = None;
+        let mut alpha = -WIN_SCORE;
+        for pos in moves {
+            if self.stop_token.is_stopped() && best.is_some() {
+                stats.stopped = true;
+                break;
+            }
+            searched.make_move(game.get_mark(self.ai_side), pos)?;
+            let score = self.search(&mut searched, &lines, 1, alpha, WIN_SCORE, &mut stats);
+            searched.undo_move();
+            // the scores of an interrupted search are only guesses
+            if self.stop_token.is_stopped() && best.is_some() {
+                stats.stopped = true;
+                break;
+            }
+            if best.is_none() || score > alpha {
+                alpha = score;
+                best = Some(pos);
+            }
+        }
+        let pos = best.ok_or(GameError::GameOver)?;
+        game.make_move(game.get_mark(self.ai_side), pos)?;
+        stats.elapsed = start.elapsed();
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{Grid, Mark::*, WinCondition};
+
+    fn three_player_game(moves: &[(usize, usize)]) -> Game {
+        let wc = WinCondition {
+            length: 4,
+            overlines: true,
+        };
+        let grid = Grid::with_win_condition(6, wc).unwrap();
+        let mut game = Game::with_players(grid, &[Cross, Nought, Triangle]).unwrap();
+        for &(x, y) in moves {
+            let pos = game.get_grid().position(x, y).unwrap();
+            game.make_move(game.get_mark_to_move(), pos).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn test_completes_own_line() {
+        // crosses on the top row, the others far apart
+        let mut game = three_player_game(&[
+            (0, 0),
+            (0, 5),
+            (5, 3),
+            (1, 0),
+            (2, 5),
+            (4, 2),
+            (2, 0),
+            (5, 5),
+            (3, 3),
+        ]);
+        ParanoidSearch::new(Player::FIRST, 3)
+            .try_make_move(&mut game)
+            .unwrap();
+        assert_eq!(Some(GameResult::Win(Player::FIRST)), game.get_result());
+    }
+
+    #[test]
+    fn test_blocks_player_after_next() {
+        // triangles threaten the left column, nought moves before them
+        let mut game = three_player_game(&[
+            (5, 5),
+            (3, 0),
+            (0, 0),
+            (5, 0),
+            (3, 5),
+            (0, 1),
+            (1, 4),
+            (4, 1),
+            (0, 2),
+        ]);
+        assert_eq!(Player::FIRST, game.get_side_to_move());
+        let mut engine = ParanoidSearch::new(Player::FIRST, 3);
+        engine.try_make_move(&mut game).unwrap();
+        assert_eq!(Ok(Some(Cross)), game.get_grid().get_at_pos(0, 3));
+    }
+}
@@ -0,0 +1,505 @@
+//! Goff's quantum tic-tac-toe. Each move puts spooky marks in two squares,
+//! entangling them. Once the entanglements close a cycle, the player who
+//! didn't close it picks where the last mark collapses to, and every mark
+//! entangled with it collapses along into a classical one.
+
+use crate::error::GameError;
+use crate::game::{GameResult, Player};
+use crate::grid::Grid;
+use std::collections::VecDeque;
+use std::fmt;
+
+pub const SIDE_LENGTH: usize = 3;
+
+const CELL_COUNT: usize = SIDE_LENGTH * SIDE_LENGTH;
+
+/// A mark with the number of the move that placed it, counted from 1.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct QuantumMark {
+    pub player: Player,
+    pub turn: usize,
+}
+
+/// Squares are numbered row by row.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum QuantumMove {
+    /// Spooky marks in two different squares without a classical mark.
+    Entangle(usize, usize),
+    /// Settles the mark that closed a cycle in one of its two squares.
+    Collapse(usize),
+    /// A classical mark in the only square left, where a pair can't go.
+    Classical(usize),
+}
+
+/// Squares holding either one classical mark or any number of spooky ones,
+/// each spooky mark shared by two squares.
+#[derive(Clone, Default, Debug)]
+pub struct QuantumBoard {
+    classical: [Option<QuantumMark>; CELL_COUNT],
+    entanglements: Vec<(QuantumMark, usize, usize)>,
+}
+
+impl QuantumBoard {
+    pub fn get_classical(&self, cell: usize) -> Option<QuantumMark> {
+        self.classical[cell]
+    }
+
+    /// The spooky marks in the square, oldest first.
+    pub fn spooky_marks(&self, cell: usize) -> impl Iterator<Item = QuantumMark> + '_ {
+        self.entanglements
+            .iter()
+            .filter(move |&&(_, a, b)| a == cell || b == cell)
+            .map(|&(mark, _, _)| mark)
+    }
+
+    /// The two squares of the spooky mark placed on the given move.
+    pub fn spooky_cells(&self, turn: usize) -> Option<(usize, usize)> {
+        self.entanglements
+            .iter()
+            .find(|(mark, _, _)| mark.turn == turn)
+            .map(|&(_, a, b)| (a, b))
+    }
+
+    /// The squares without a classical mark.
+    pub fn open_cells(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..CELL_COUNT).filter(move |&cell| self.classical[cell].is_none())
+    }
+
+    /// Whether the squares are joined through entanglements, so that a pair
+    /// of spooky marks in them would close a cycle.
+    fn are_entangled(&self, from: usize, to: usize) -> bool {
+        let mut seen = [false; CELL_COUNT];
+        let mut queue = VecDeque::from(vec![from]);
+        seen[from] = true;
+        while let Some(cell) = queue.pop_front() {
+            if cell == to {
+                return true;
+            }
+            for &(_, a, b) in &self.entanglements {
+                let next = match cell {
+                    _ if cell == a => b,
+                    _ if cell == b => a,
+                    _ => continue,
+                };
+                if !seen[next] {
+                    seen[next] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+        false
+    }
+
+    /// Makes the mark of the given move classical in the square, then the
+    /// marks sharing a square with it in their other squares, and so on.
+    fn collapse(&mut self, turn: usize, cell: usize) {
+        let mut queue = VecDeque::from(vec![(turn, cell)]);
+        while let Some((turn, cell)) = queue.pop_front() {
+            let ind = match self
+                .entanglements
+                .iter()
+                .position(|(m, _, _)| m.turn == turn)
+            {
+                Some(ind) => ind,
+                None => continue,
+            };
+            let (mark, _, _) = self.entanglements.swap_remove(ind);
+            self.classical[cell] = Some(mark);
+            for &(other, a, b) in &self.entanglements {
+                if a == cell {
+                    queue.push_back((other.turn, b));
+                } else if b == cell {
+                    queue.push_back((other.turn, a));
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct QuantumGame {
+    board: QuantumBoard,
+    side_to_move: Player,
+    turn: usize,
+    cycle: Option<usize>,
+    result: Option<GameResult>,
+    scores: [f32; 2],
+}
+
+impl Default for QuantumGame {
+    fn default() -> QuantumGame {
+        QuantumGame::new()
+    }
+}
+
+impl QuantumGame {
+    pub fn new() -> QuantumGame {
+        QuantumGame {
+            board: QuantumBoard::default(),
+            side_to_move: Player::FIRST,
+            turn: 1,
+            cycle: None,
+            result: None,
+            scores: [0.0; 2],
+        }
+    }
+
+    pub fn get_board(&self) -> &QuantumBoard {
+        &self.board
+    }
+
+    pub fn get_side_to_move(&self) -> Player {
+        self.side_to_move
+    }
+
+    pub fn get_result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// The move whose spooky mark closed a cycle, which the side to move has
+    /// to collapse before anything else.
+    pub fn get_cycle(&self) -> Option<usize> {
+        self.cycle
+    }
+
+    /// Points of the player once the game is over: one for a line, or two
+    /// for two lines made at once. When a collapse gives both players a
+    /// line, the one whose line was finished by the earlier move gets a
+    /// point and the other half a point.
+    pub fn get_score(&self, player: Player) -> f32 {
+        self.scores.get(player.get_seat()).copied().unwrap_or(0.0)
+    }
+
+    /// The square at the given column and row.
+    pub fn cell(&self, x: usize, y: usize) -> Result<usize, GameError> {
+        if x < SIDE_LENGTH && y < SIDE_LENGTH {
+            Ok(y * SIDE_LENGTH + x)
+        } else {
+            Err(GameError::OutOfBounds)
+        }
+    }
+
+    pub fn legal_moves(&self) -> Vec<QuantumMove> {
+        if self.is_over() {
+            return Vec::new();
+        }
+        if let Some((a, b)) = self.cycle.and_then(|turn| self.board.spooky_cells(turn)) {
+            return vec![QuantumMove::Collapse(a), QuantumMove::Collapse(b)];
+        }
+        let open: Vec<usize> = self.board.open_cells().collect();
+        if let [cell] = open[..] {
+            return vec![QuantumMove::Classical(cell)];
+        }
+        open.iter()
+            .enumerate()
+            .flat_map(|(i, &a)| {
+                open[i + 1..]
+                    .iter()
+                    .map(move |&b| QuantumMove::Entangle(a, b))
+            })
+            .collect()
+    }
+
+    pub fn make_move(&mut self, what: Player, m: QuantumMove) -> Result<(), GameError> {
+        if self.is_over() {
+            return Err(GameError::GameOver);
+        }
+        if what != self.side_to_move {
+            return Err(GameError::WrongSide(what));
+        }
+        let open_count = self.board.open_cells().count();
+        match m {
+            QuantumMove::Collapse(cell) => {
+                let turn = self.cycle.ok_or(GameError::ForbiddenMove)?;
+                match self.board.spooky_cells(turn) {
+                    Some((a, b)) if cell == a || cell == b => self.board.collapse(turn, cell),
+                    _ => return Err(GameError::ForbiddenMove),
+                }
+                self.cycle = None;
+                // the chooser goes on with a move of their own
+                self.check_result();
+                return Ok(());
+            }
+            _ if self.cycle.is_some() => return Err(GameError::CollapsePending),
+            QuantumMove::Entangle(a, b) => {
+                self.check_open(a)?;
+                self.check_open(b)?;
+                if a == b || open_count < 2 {
+                    return Err(GameError::ForbiddenMove);
+                }
+                if self.board.are_entangled(a, b) {
+                    self.cycle = Some(self.turn);
+                }
+                let mark = self.mark();
+                self.board.entanglements.push((mark, a, b));
+            }
+            QuantumMove::Classical(cell) => {
+                self.check_open(cell)?;
+                if open_count != 1 {
+                    return Err(GameError::ForbiddenMove);
+                }
+                self.board.classical[cell] = Some(self.mark());
+                self.check_result();
+            }
+        }
+        self.turn += 1;
+        self.side_to_move = what.opponent();
+        Ok(())
+    }
+
+    fn mark(&self) -> QuantumMark {
+        QuantumMark {
+            player: self.side_to_move,
+            turn: self.turn,
+        }
+    }
+
+    fn check_open(&self, cell: usize) -> Result<(), GameError> {
+        match self.board.classical.get(cell) {
+            None => Err(GameError::OutOfBounds),
+            Some(Some(mark)) => Err(GameError::OccupiedCell(mark.player.standard_mark())),
+            Some(None) => Ok(()),
+        }
+    }
+
+    fn check_result(&mut self) {
+        // for each player the number of lines and the earliest move that
+        // finished one of them
+        let mut lines = [(0, usize::MAX); 2];
+        for line in Grid::new(SIDE_LENGTH).lines() {
+            let marks: Option<Vec<QuantumMark>> = line
+                .iter()
+                .map(|pos| self.board.classical[pos.ind()])
+                .collect();
+            let marks = match marks {
+                Some(marks) => marks,
+                None => continue,
+            };
+            let player = marks[0].player;
+            if marks.iter().all(|mark| mark.player == player) {
+                let last = marks.iter().map(|mark| mark.turn).max().unwrap_or(0);
+                let entry = &mut lines[player.get_seat()];
+                *entry = (entry.0 + 1, entry.1.min(last));
+            }
+        }
+
+        self.scores = match lines {
+            [(0, _), (0, _)] => [0.0; 2],
+            [(crosses, _), (0, _)] => [crosses as f32, 0.0],
+            [(0, _), (noughts, _)] => [0.0, noughts as f32],
+            [(_, cross_last), (_, nought_last)] if cross_last < nought_last => [1.0, 0.5],
+            _ => [0.5, 1.0],
+        };
+        self.result = if self.scores[0] > self.scores[1] {
+            Some(GameResult::Win(Player::FIRST))
+        } else if self.scores[1] > self.scores[0] {
+            Some(GameResult::Win(Player::SECOND))
+        } else if self.board.open_cells().next().is_none() {
+            Some(GameResult::Draw)
+        } else {
+            None
+        };
+    }
+}
+
+/// Renders each square as three rows of three slots, where the spooky mark
+/// of move n takes slot n in lowercase. Classical marks fill the middle of
+/// their square in uppercase.
+impl fmt::Display for QuantumGame {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let separator = format!("\n   {}+", "+---------".repeat(SIDE_LENGTH));
+        write!(formatter, "\n   ")?;
+        for col in 0..SIDE_LENGTH {
+            write!(formatter, "{:^10}", col)?;
+        }
+        for row in 0..SIDE_LENGTH {
+            write!(formatter, "{}", separator)?;
+            for slot_row in 0..SIDE_LENGTH {
+                let label = if slot_row == 1 {
+                    row.to_string()
+                } else {
+                    String::new()
+                };
+                write!(formatter, "\n{:^3}|", label)?;
+                for col in 0..SIDE_LENGTH {
+                    let cell = row * SIDE_LENGTH + col;
+                    match self.board.classical[cell] {
+                        Some(mark) if slot_row == 1 => {
+                            let text =
+                                format!("{}{}", mark.player.standard_mark().symbol(), mark.turn);
+                            write!(formatter, "{:^9}|", text)?;
+                        }
+                        Some(_) => write!(formatter, "{:9}|", "")?,
+                        None => {
+                            for slot in 0..SIDE_LENGTH {
+                                let turn = slot_row * SIDE_LENGTH + slot + 1;
+                                match self.board.spooky_marks(cell).find(|m| m.turn == turn) {
+                                    Some(mark) => {
+                                        let symbol = mark
+                                            .player
+                                            .standard_mark()
+                                            .symbol()
+                                            .to_ascii_lowercase();
+                                        write!(formatter, " {}{}", symbol, turn)?;
+                                    }
+                                    None => write!(formatter, "   ")?,
+                                }
+                            }
+                            write!(formatter, "|")?;
+                        }
+                    }
+                }
+            }
+        }
+        writeln!(formatter, "{}", separator)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QuantumMove::*, *};
+    use crate::grid::Mark::*;
+
+    const CROSS: Player = Player::FIRST;
+    const NOUGHT: Player = Player::SECOND;
+
+    fn play(game: &mut QuantumGame, m: QuantumMove) -> Result<(), GameError> {
+        game.make_move(game.get_side_to_move(), m)
+    }
+
+    #[test]
+    fn test_cycle_waits_for_collapse_by_other_player() {
+        let mut game = QuantumGame::new();
+        assert_eq!(36, game.legal_moves().len());
+        play(&mut game, Entangle(0, 1)).unwrap();
+        play(&mut game, Entangle(1, 2)).unwrap();
+        assert_eq!(
+            Err(GameError::ForbiddenMove),
+            play(&mut game, Entangle(3, 3))
+        );
+        assert_eq!(None, game.get_cycle());
+
+        // X closes the cycle 0-1-2, O decides where X3 ends up
+        play(&mut game, Entangle(2, 0)).unwrap();
+        assert_eq!(Some(3), game.get_cycle());
+        assert_eq!(NOUGHT, game.get_side_to_move());
+        assert_eq!(vec![Collapse(2), Collapse(0)], game.legal_moves());
+        assert_eq!(
+            Err(GameError::CollapsePending),
+            play(&mut game, Entangle(4, 5))
+        );
+        assert_eq!(Err(GameError::ForbiddenMove), play(&mut game, Collapse(1)));
+
+        play(&mut game, Collapse(0)).unwrap();
+        let board = game.get_board();
+        let turns: Vec<Option<usize>> = (0..3)
+            .map(|cell| board.get_classical(cell).map(|mark| mark.turn))
+            .collect();
+        assert_eq!(vec![Some(3), Some(1), Some(2)], turns);
+        assert_eq!(0, board.spooky_marks(0).count());
+        // the collapse doesn't use up the chooser's move
+        assert_eq!(NOUGHT, game.get_side_to_move());
+        assert_eq!(
+            Err(GameError::OccupiedCell(Cross)),
+            play(&mut game, Entangle(0, 4))
+        );
+    }
+
+    #[test]
+    fn test_collapse_completes_line() {
+        let mut game = QuantumGame::new();
+        let moves = [
+            Entangle(0, 6),
+            Entangle(6, 7),
+            Entangle(1, 7),
+            Entangle(3, 4),
+        ];
+        for &m in &moves {
+            play(&mut game, m).unwrap();
+        }
+        // X5 closes the cycle 0-6-7-1, sending X1 to 6, O2 to 7 and X3 to 1
+        play(&mut game, Entangle(0, 1)).unwrap();
+        play(&mut game, Collapse(0)).unwrap();
+        let turn =
+            |game: &QuantumGame, cell| game.get_board().get_classical(cell).map(|mark| mark.turn);
+        let turns: Vec<Option<usize>> = [0, 1, 6, 7, 3]
+            .iter()
+            .map(|&cell| turn(&game, cell))
+            .collect();
+        assert_eq!(vec![Some(5), Some(3), Some(1), Some(2), None], turns);
+
+        // O8 closes the cycle 2-4-3-8, and X collapses it into the top row
+        for &m in &[Entangle(3, 8), Entangle(2, 4), Entangle(2, 8)] {
+            play(&mut game, m).unwrap();
+        }
+        assert_eq!(CROSS, game.get_side_to_move());
+        play(&mut game, Collapse(8)).unwrap();
+        assert_eq!(Some(7), turn(&game, 2));
+        assert_eq!(Some(GameResult::Win(CROSS)), game.get_result());
+        assert_eq!(1.0, game.get_score(CROSS));
+        assert_eq!(0.0, game.get_score(NOUGHT));
+    }
+
+    #[test]
+    fn test_simultaneous_lines_score_earlier_line_higher() {
+        let mut game = QuantumGame::new();
+        let mark = |player, turn| Some(QuantumMark { player, turn });
+        // X on the top row finished by X5, O on the bottom row by O6
+        for (cell, turn) in [(0, 1), (1, 3), (2, 5)] {
+            game.board.classical[cell] = mark(CROSS, turn);
+        }
+        for (cell, turn) in [(6, 2), (7, 4), (8, 6)] {
+            game.board.classical[cell] = mark(NOUGHT, turn);
+        }
+        game.check_result();
+        assert_eq!(Some(GameResult::Win(CROSS)), game.get_result());
+        assert_eq!(1.0, game.get_score(CROSS));
+        assert_eq!(0.5, game.get_score(NOUGHT));
+    }
+
+    #[test]
+    fn test_last_square_takes_classical_mark() {
+        let mut game = QuantumGame::new();
+        let moves = [
+            Entangle(0, 1),
+            Entangle(0, 1),
+            Collapse(0),
+            Entangle(2, 3),
+            Entangle(2, 3),
+            Collapse(3),
+            Entangle(4, 5),
+            Entangle(4, 5),
+            Collapse(4),
+            Entangle(6, 7),
+            Entangle(6, 7),
+            Collapse(7),
+        ];
+        for &m in &moves {
+            play(&mut game, m).unwrap();
+        }
+        assert_eq!(vec![Classical(8)], game.legal_moves());
+        assert_eq!(
+            Err(GameError::ForbiddenMove),
+            play(&mut game, Entangle(8, 8))
+        );
+        play(&mut game, Classical(8)).unwrap();
+        assert!(game.is_over());
+    }
+
+    #[test]
+    fn test_render_superpositions() {
+        let mut game = QuantumGame::new();
+        play(&mut game, Entangle(0, 4)).unwrap();
+        play(&mut game, Entangle(4, 8)).unwrap();
+        let rendered = game.to_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!("       0         1         2     ", lines[1]);
+        assert_eq!("   | x1      |         |         |", lines[3]);
+        assert_eq!("   |         | x1 o2   |         |", lines[7]);
+    }
+}
@@ -0,0 +1,49 @@
+//! Qubic: tic-tac-toe on a 4x4x4 cube, where rows may also run through the
+//! layers, making 76 lines to fight for.
+
+use crate::error::GameError;
+use crate::game::Game;
+use crate::grid::{Grid, WinCondition};
+
+pub const DEFAULT_SIDE_LENGTH: usize = 4;
+
+/// How deep the AI searches a cube, which is too large to solve in a
+/// reasonable time.
+pub const SEARCH_DEPTH: usize = 4;
+
+pub fn new_game(side_length: usize) -> Result<Game, GameError> {
+    Game::from_grid(Grid::cube(
+        side_length,
+        WinCondition::full_line(side_length),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameResult, Player};
+    use crate::grid::Mark::*;
+
+    #[test]
+    fn test_row_through_layers_wins() {
+        let mut game = new_game(DEFAULT_SIDE_LENGTH).unwrap();
+        for layer in 0..DEFAULT_SIDE_LENGTH {
+            assert_eq!(None, game.get_result());
+            let grid = game.get_grid();
+            let diagonal = grid.position_3d(layer, 3 - layer, layer).unwrap();
+            let elsewhere = grid.position_3d(layer, layer, 0).unwrap();
+            game.make_move(Cross, diagonal).unwrap();
+            if layer + 1 < DEFAULT_SIDE_LENGTH {
+                game.make_move(Nought, elsewhere).unwrap();
+            }
+        }
+        assert_eq!(Some(GameResult::Win(Player::FIRST)), game.get_result());
+    }
+
+    #[test]
+    fn test_cube_fills_up() {
+        let game = new_game(3).unwrap();
+        assert_eq!(27, game.legal_moves().count());
+        assert_eq!(3, game.get_grid().get_depth());
+    }
+}
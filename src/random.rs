@@ -0,0 +1,33 @@
+//! A small pseudo-random number generator for the engines and board
+//! layouts that need one.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The xorshift64* generator.
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        // xorshift gets stuck on zero
+        Random { state: seed | 1 }
+    }
+
+    /// Seeded with the current time.
+    pub fn from_time() -> Random {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_nanos() as u64);
+        Random::new(seed)
+    }
+
+    /// Returns a number below the bound, which must not be zero.
+    pub fn below(&mut self, bound: usize) -> usize {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 32) as usize % bound
+    }
+}
@@ -0,0 +1,240 @@
+//! Renju restrictions: Cross, who moves first, may not make a double-three,
+//! a double-four or an overline, unless the move makes exactly five.
+//!
+//! A four is a row that becomes exactly five with one more mark, and a
+//! three is a row that becomes a straight four (a four with both ends
+//! open) with one more allowed mark.
+
+use crate::grid::{Grid, Mark, Position};
+
+const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+/// Checks whether Cross is forbidden to play at the empty position.
+pub fn is_forbidden(grid: &Grid, pos: Position) -> bool {
+    if !grid.is_empty_cell(pos) || !has_enough_support(grid, pos) {
+        return false;
+    }
+    let mut grid = grid.clone();
+    grid.set(pos, Mark::Cross).unwrap();
+    is_forbidden_with_move(&mut grid, pos)
+}
+
+/// All positions where Cross is forbidden to play.
+pub fn forbidden_points(grid: &Grid) -> Vec<Position> {
+    grid.empty_cells()
+        .filter(|&pos| is_forbidden(grid, pos))
+        .collect()
+}
+
+/// Even the smallest forbidden shape, the double-three, needs four other
+/// crosses on the lines through the position.
+fn has_enough_support(grid: &Grid, pos: Position) -> bool {
+    let support = DIRECTIONS
+        .iter()
+        .flat_map(|&dir| (-5..=5).map(move |k| offset(grid, pos, dir, k)))
+        .filter(|&cell| cell.is_some_and(|cell| grid.get(cell) == Some(Mark::Cross)))
+        .count();
+    support >= 4
+}
+
+/// Decides about the cross just placed at the position.
+fn is_forbidden_with_move(grid: &mut Grid, pos: Position) -> bool {
+    let runs: Vec<usize> = DIRECTIONS.iter().map(|&dir| run(grid, pos, dir)).collect();
+    if runs.contains(&5) {
+        return false;
+    }
+    if runs.iter().any(|&length| length > 5) {
+        return true;
+    }
+    let four_count: usize = DIRECTIONS.iter().map(|&dir| fours(grid, pos, dir)).sum();
+    if four_count >= 2 {
+        return true;
+    }
+    let three_count = DIRECTIONS
+        .iter()
+        .filter(|&&dir| fours(grid, pos, dir) == 0 && is_three(grid, pos, dir))
+        .count();
+    three_count >= 2
+}
+
+/// Number of fours through the position along the direction. A straight
+/// four has two completing points but counts once.
+fn fours(grid: &Grid, pos: Position, dir: (isize, isize)) -> usize {
+    let completions: Vec<isize> = (-4..=4)
+        .filter(|&k| {
+            offset(grid, pos, dir, k).is_some_and(|cell| {
+                grid.is_empty_cell(cell) && makes_five_with(grid, cell, dir, -k)
+            })
+        })
+        .collect();
+    match completions[..] {
+        [first, second] if second - first == 5 => 1,
+        _ => completions.len().min(2),
+    }
+}
+
+/// Whether one more allowed cross turns the row through the position into
+/// a straight four.
+fn is_three(grid: &mut Grid, pos: Position, dir: (isize, isize)) -> bool {
+    (-4..=4).any(|k| {
+        let cell = match offset(grid, pos, dir, k) {
+            Some(cell) if grid.is_empty_cell(cell) => cell,
+            _ => return false,
+        };
+        grid.set(cell, Mark::Cross).unwrap();
+        let straight = is_straight_four(grid, pos, dir) && !is_forbidden_with_move(grid, cell);
+        grid.unset(cell);
+        straight
+    })
+}
+
+fn is_straight_four(grid: &Grid, pos: Position, dir: (isize, isize)) -> bool {
+    let (back, forward) = (extent(grid, pos, dir, -1), extent(grid, pos, dir, 1));
+    if back + forward + 1 != 4 {
+        return false;
+    }
+    [-(back as isize) - 1, forward as isize + 1]
+        .iter()
+        .all(|&k| {
+            offset(grid, pos, dir, k).is_some_and(|cell| {
+                grid.is_empty_cell(cell) && makes_five_with(grid, cell, dir, -k)
+            })
+        })
+}
+
+/// Whether a cross at the empty cell makes exactly five along the direction
+/// in a row that includes the cell at offset k from it.
+fn makes_five_with(grid: &Grid, cell: Position, dir: (isize, isize), k: isize) -> bool {
+    let (back, forward) = (extent(grid, cell, dir, -1), extent(grid, cell, dir, 1));
+    back + forward + 1 == 5 && -(back as isize) <= k && k <= forward as isize
+}
+
+/// Length of the row of crosses through the position along the direction.
+fn run(grid: &Grid, pos: Position, dir: (isize, isize)) -> usize {
+    extent(grid, pos, dir, -1) + 1 + extent(grid, pos, dir, 1)
+}
+
+/// Number of consecutive crosses next to the position in one sense of the
+/// direction, not counting the position itself.
+fn extent(grid: &Grid, pos: Position, dir: (isize, isize), sense: isize) -> usize {
+    (1..)
+        .take_while(|&k| {
+            offset(grid, pos, dir, sense * k)
+                .is_some_and(|cell| grid.get(cell) == Some(Mark::Cross))
+        })
+        .count()
+}
+
+fn offset(grid: &Grid, pos: Position, (dx, dy): (isize, isize), k: isize) -> Option<Position> {
+    let x = pos.x() as isize + dx * k;
+    let y = pos.y() as isize + dy * k;
+    if x < 0 || y < 0 {
+        return None;
+    }
+    grid.position(x as usize, y as usize).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a 15x15 grid from rows of 'X', 'O' and '.', returns it with
+    /// the position marked '*'.
+    fn board(rows: &[&str]) -> (Grid, Position) {
+        let mut g = Grid::new(15);
+        let mut candidate = None;
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                match c {
+                    'X' => g.set_at_pos(x, y, Mark::Cross).unwrap(),
+                    'O' => g.set_at_pos(x, y, Mark::Nought).unwrap(),
+                    '*' => candidate = g.position(x, y).ok(),
+                    _ => {}
+                }
+            }
+        }
+        (g, candidate.unwrap())
+    }
+
+    #[test]
+    fn test_double_three_is_forbidden() {
+        let (g, pos) = board(&[
+            "...............",
+            "...............",
+            ".......X.......",
+            ".......X.......",
+            ".....XX*.......",
+        ]);
+        assert!(is_forbidden(&g, pos));
+    }
+
+    #[test]
+    fn test_blocked_three_does_not_count() {
+        let (g, pos) = board(&[
+            "...............",
+            "...............",
+            ".......X.......",
+            ".......X.......",
+            "....OXX*.......",
+        ]);
+        assert!(!is_forbidden(&g, pos));
+    }
+
+    #[test]
+    fn test_double_four_is_forbidden() {
+        let (g, pos) = board(&[
+            "...............",
+            ".......X.......",
+            ".......X.......",
+            ".......X.......",
+            "....XXX*.......",
+        ]);
+        assert!(is_forbidden(&g, pos));
+
+        // two fours along a single line
+        let (g, pos) = board(&["...X.X*X.X....."]);
+        assert!(is_forbidden(&g, pos));
+    }
+
+    #[test]
+    fn test_four_three_is_allowed() {
+        let (g, pos) = board(&[
+            "...............",
+            "...............",
+            ".......X.......",
+            ".......X.......",
+            "....XXX*.......",
+        ]);
+        assert!(!is_forbidden(&g, pos));
+    }
+
+    #[test]
+    fn test_overline_is_forbidden() {
+        let (g, pos) = board(&["..XXX*XX......."]);
+        assert!(is_forbidden(&g, pos));
+    }
+
+    #[test]
+    fn test_five_overrides_restrictions() {
+        let (g, pos) = board(&[
+            "...............",
+            ".......X.......",
+            ".......X.......",
+            ".......X.......",
+            "...XXXX*.......",
+        ]);
+        assert!(!is_forbidden(&g, pos));
+    }
+
+    #[test]
+    fn test_forbidden_points() {
+        let (g, pos) = board(&[
+            "...............",
+            "...............",
+            ".......X.......",
+            ".......X.......",
+            ".....XX*.......",
+        ]);
+        assert_eq!(vec![pos], forbidden_points(&g));
+    }
+}
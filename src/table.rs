@@ -0,0 +1,307 @@
+use crate::ai::{self, Engine, SearchStats, StopToken};
+use crate::error::GameError;
+use crate::game::{Game, Player};
+use crate::grid::{Grid, Mark, Position};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Side lengths for which every position fits into a table.
+pub const MAX_TABLE_SIDE_LENGTH: usize = 3;
+
+const MAGIC: &[u8] = b"TTT\0";
+const NO_MOVE: u8 = u8::MAX;
+
+/// Best move for every reachable position of a small grid.
+///
+/// The file format is the magic bytes, one byte with the side length and
+/// then one byte per position: the index of the best move, or 255 if the
+/// position is finished or can't be reached. Positions are numbered by
+/// reading the cells as a base-3 number with 0 for empty, 1 for a cross
+/// and 2 for a nought, the first cell being the least significant digit.
+pub struct PerfectPlayTable {
+    side_length: usize,
+    moves: Vec<u8>,
+}
+
+impl PerfectPlayTable {
+    /// Solves every position reachable from the empty grid.
+    pub fn generate(side_length: usize) -> Result<PerfectPlayTable, GameError> {
+        if !(2..=MAX_TABLE_SIDE_LENGTH).contains(&side_length) {
+            return Err(GameError::InvalidSize(side_length));
+        }
+        let mut table = PerfectPlayTable {
+            side_length,
+            moves: vec![NO_MOVE; 3usize.pow((side_length * side_length) as u32)],
+        };
+        let mut solvers = [
+            ai::AlphaBetaPruning::new(Player::FIRST),
+            ai::AlphaBetaPruning::new(Player::SECOND),
+        ];
+        for solver in solvers.iter_mut() {
+            solver.set_verbose(false);
+        }
+        table.solve(&mut Game::new(side_length), &mut solvers);
+        Ok(table)
+    }
+
+    pub fn load(path: &Path) -> io::Result<PerfectPlayTable> {
+        let contents = fs::read(path)?;
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid table file");
+        if !contents.starts_with(MAGIC) {
+            return Err(invalid());
+        }
+        let side_length = *contents.get(MAGIC.len()).ok_or_else(invalid)? as usize;
+        if !(2..=MAX_TABLE_SIDE_LENGTH).contains(&side_length) {
+            return Err(invalid());
+        }
+        let moves = contents[MAGIC.len() + 1..].to_vec();
+        let size = side_length * side_length;
+        if moves.len() != 3usize.pow(size as u32)
+            || moves.iter().any(|&m| m != NO_MOVE && m as usize >= size)
+        {
+            return Err(invalid());
+        }
+        Ok(PerfectPlayTable { side_length, moves })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut contents = MAGIC.to_vec();
+        contents.push(self.side_length as u8);
+        contents.extend_from_slice(&self.moves);
+        fs::write(path, contents)
+    }
+
+    pub fn get_side_length(&self) -> usize {
+        self.side_length
+    }
+
+    /// Returns the best move in the given grid under the standard rules, if
+    /// any. Only plain grids of the side length solved are covered.
+    pub fn lookup(&self, grid: &Grid) -> Option<Position> {
+        if grid.get_side_length() != self.side_length || !grid.is_plain() {
+            return None;
+        }
+        match self.moves[position_code(grid)?] {
+            NO_MOVE => None,
+            ind => grid.position_of_ind(ind as usize).ok(),
+        }
+    }
+
+    fn solve(&mut self, game: &mut Game, solvers: &mut [ai::AlphaBetaPruning; 2]) {
+        let code = match position_code(game.get_grid()) {
+            Some(code) if self.moves[code] == NO_MOVE && !game.is_over() => code,
+            _ => return,
+        };
+
+        let side = game.get_side_to_move();
+        let mut solved = game.clone();
+        solvers[side.get_seat()]
+            .try_make_move(&mut solved)
+            .expect("unfinished position has moves");
+        let best_pos = solved.undo_move().unwrap();
+        self.moves[code] = best_pos.ind() as u8;
+
+        let moves: Vec<Position> = game.legal_moves().collect();
+        for pos in moves {
+            game.make_move(game.get_mark(side), pos).unwrap();
+            self.solve(game, solvers);
+            game.undo_move();
+        }
+    }
+}
+
+/// The grid as a number in base 3, None if it has marks of players other
+/// than X and O.
+fn position_code(grid: &Grid) -> Option<usize> {
+    grid.positions()
+        .try_fold((0, 1), |(code, weight), pos| {
+            let digit = match grid.get(pos) {
+                None => 0,
+                Some(Mark::Cross) => 1,
+                Some(Mark::Nought) => 2,
+                Some(Mark::Triangle) | Some(Mark::Square) => return None,
+            };
+            Some((code + digit * weight, weight * 3))
+        })
+        .map(|(code, _)| code)
+}
+
+/// Plays instantly by looking moves up in a perfect-play table.
+pub struct TableEngine {
+    ai_side: Player,
+    table: Arc<PerfectPlayTable>,
+    stop_token: StopToken,
+}
+
+impl TableEngine {
+    pub fn new(ai_side: Player, table: Arc<PerfectPlayTable>) -> TableEngine {
+        TableEngine {
+            ai_side,
+            table,
+            stop_token: StopToken::default(),
+        }
+    }
+}
+
+impl Engine for TableEngine {
+    fn get_ai_side(&self) -> Player {
+        self.ai_side
+    }
+
+    fn get_stop_token(&self) -> StopToken {
+        self.stop_token.clone()
+    }
+
+    fn try_make_move(&mut self, game: &mut Game) -> Result<SearchStats, GameError> {
+        let side_length = game.get_grid().get_side_length();
+        if side_length != self.table.get_side_length() || !game.get_grid().is_plain() {
+            return Err(GameError::InvalidSize(side_length));
+        }
+        if !game.has_standard_rules() {
+            return Err(GameError::UnsupportedRules);
+        }
+        if game.get_player_count() != 2 {
+            return Err(GameError::InvalidPlayers);
+        }
+        if game.is_over() {
+            return Err(GameError::GameOver);
+        }
+        let pos = self
+            .table
+            .lookup(game.get_grid())
+            .ok_or(GameError::GameOver)?;
+        game.make_move(game.get_mark(self.ai_side), pos)?;
+        Ok(SearchStats {
+            nodes_visited: 1,
+            ..SearchStats::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameResult;
+    use crate::grid::WinCondition;
+    use std::collections::HashSet;
+
+    fn play_out(game: &mut Game, engines: &mut [Box<dyn Engine>; 2]) {
+        while engines[game.get_side_to_move().get_seat()]
+            .try_make_move(game)
+            .is_ok()
+        {}
+    }
+
+    fn for_each_position(
+        game: &mut Game,
+        seen: &mut HashSet<Grid>,
+        action: &mut impl FnMut(&Game),
+    ) {
+        if game.is_over() || !seen.insert(game.get_grid().clone()) {
+            return;
+        }
+        action(game);
+        let moves: Vec<Position> = game.legal_moves().collect();
+        for pos in moves {
+            game.make_move(game.get_mark_to_move(), pos).unwrap();
+            for_each_position(game, seen, action);
+            game.undo_move();
+        }
+    }
+
+    #[test]
+    fn test_rejects_unsolvable_size() {
+        assert_eq!(
+            GameError::InvalidSize(4),
+            PerfectPlayTable::generate(4).err().unwrap()
+        );
+        assert!(PerfectPlayTable::generate(1).is_err());
+    }
+
+    #[test]
+    fn test_perfect_play_is_a_draw() {
+        let table = Arc::new(PerfectPlayTable::generate(3).unwrap());
+        let mut engines: [Box<dyn Engine>; 2] = [
+            Box::new(TableEngine::new(Player::FIRST, table.clone())),
+            Box::new(TableEngine::new(Player::SECOND, table)),
+        ];
+        let mut game = Game::new(3);
+        play_out(&mut game, &mut engines);
+
+        assert_eq!(Some(GameResult::Draw), game.get_result());
+    }
+
+    #[test]
+    fn test_table_agrees_with_search() {
+        let table = Arc::new(PerfectPlayTable::generate(3).unwrap());
+        let mut table_engines: [Box<dyn Engine>; 2] = [
+            Box::new(TableEngine::new(Player::FIRST, table.clone())),
+            Box::new(TableEngine::new(Player::SECOND, table.clone())),
+        ];
+        let quiet_search = |side| {
+            let mut engine = ai::AlphaBetaPruning::new(side);
+            engine.set_verbose(false);
+            Box::new(engine)
+        };
+        let mut search_engines: [Box<dyn Engine>; 2] =
+            [quiet_search(Player::FIRST), quiet_search(Player::SECOND)];
+
+        let mut positions = 0;
+        let mut seen = HashSet::new();
+        for_each_position(&mut Game::new(3), &mut seen, &mut |position| {
+            positions += 1;
+            assert!(table.lookup(position.get_grid()).is_some());
+
+            let mut by_table = position.clone();
+            play_out(&mut by_table, &mut table_engines);
+            let mut by_search = position.clone();
+            play_out(&mut by_search, &mut search_engines);
+            assert_eq!(
+                by_search.get_result(),
+                by_table.get_result(),
+                "{}",
+                position.get_grid()
+            );
+        });
+        assert_eq!(4520, positions);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let table = PerfectPlayTable::generate(2).unwrap();
+        let path = std::env::temp_dir().join("ttt_test_table_save_and_load.table");
+        table.save(&path).unwrap();
+        let loaded = PerfectPlayTable::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(2, loaded.get_side_length());
+        assert_eq!(table.moves, loaded.moves);
+        assert!(loaded.lookup(&Grid::new(2)).is_some());
+        assert_eq!(None, loaded.lookup(&Grid::new(3)));
+    }
+
+    #[test]
+    fn test_only_standard_games_are_covered() {
+        let table = Arc::new(PerfectPlayTable::generate(3).unwrap());
+        let tall = Grid::rectangular(3, 6, WinCondition::full_line(3)).unwrap();
+        assert_eq!(None, table.lookup(&tall));
+        let short_rows = WinCondition {
+            length: 2,
+            overlines: true,
+        };
+        assert_eq!(
+            None,
+            table.lookup(&Grid::with_win_condition(3, short_rows).unwrap())
+        );
+
+        let mut engine = TableEngine::new(Player::FIRST, table);
+        let mut game = Game::new(3);
+        game.set_misere(true);
+        assert_eq!(
+            Err(GameError::UnsupportedRules),
+            engine.try_make_move(&mut game).map(|_| ())
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! Ultimate tic-tac-toe: a 3x3 meta-board of 3x3 grids. The cell a move is
+//! made in picks the board the opponent has to play on next, and winning a
+//! board claims the matching cell of the meta-board.
+
+use crate::error::GameError;
+use crate::game::{GameResult, Player};
+use crate::grid::{get_winner, Grid, Mark, Position};
+use std::fmt;
+
+/// Side length of the meta-board and of each of its boards.
+pub const SIDE_LENGTH: usize = 3;
+
+const BOARD_COUNT: usize = SIDE_LENGTH * SIDE_LENGTH;
+
+/// A cell of one of the boards. Boards are numbered like the cells of the
+/// meta-board, row by row.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct UltimateMove {
+    pub board: usize,
+    pub pos: Position,
+}
+
+#[derive(Clone, Debug)]
+pub struct UltimateGame {
+    boards: Vec<Grid>,
+    meta: Grid,
+    side_to_move: Player,
+    active_board: Option<usize>,
+    result: Option<GameResult>,
+    history: Vec<(UltimateMove, Option<usize>)>,
+}
+
+impl Default for UltimateGame {
+    fn default() -> UltimateGame {
+        UltimateGame::new()
+    }
+}
+
+impl UltimateGame {
+    pub fn new() -> UltimateGame {
+        UltimateGame {
+            boards: vec![Grid::new(SIDE_LENGTH); BOARD_COUNT],
+            meta: Grid::new(SIDE_LENGTH),
+            side_to_move: Player::FIRST,
+            active_board: None,
+            result: None,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn get_boards(&self) -> &[Grid] {
+        &self.boards
+    }
+
+    /// The boards won so far, marked with their winners.
+    pub fn get_meta_grid(&self) -> &Grid {
+        &self.meta
+    }
+
+    pub fn get_side_to_move(&self) -> Player {
+        self.side_to_move
+    }
+
+    pub fn get_result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// The board the side to move was sent to, or None if it may pick any
+    /// board that is still undecided.
+    pub fn get_active_board(&self) -> Option<usize> {
+        self.active_board
+    }
+
+    /// Whether the board is won or full, so that nobody can play on it.
+    pub fn is_decided(&self, board: usize) -> bool {
+        self.meta.get_at_ind(board) != Ok(None) || self.boards[board].is_full()
+    }
+
+    pub fn is_playable(&self, board: usize) -> bool {
+        !self.is_over()
+            && !self.is_decided(board)
+            && self.active_board.is_none_or(|active| active == board)
+    }
+
+    /// The move at the given column and row of the rendered board.
+    pub fn position(&self, x: usize, y: usize) -> Result<UltimateMove, GameError> {
+        let side = SIDE_LENGTH * SIDE_LENGTH;
+        if x >= side || y >= side {
+            return Err(GameError::OutOfBounds);
+        }
+        let board = (y / SIDE_LENGTH) * SIDE_LENGTH + x / SIDE_LENGTH;
+        let pos = self.boards[board].position(x % SIDE_LENGTH, y % SIDE_LENGTH)?;
+        Ok(UltimateMove { board, pos })
+    }
+
+    /// Iterates over the empty cells of the playable boards.
+    pub fn legal_moves(&self) -> impl Iterator<Item = UltimateMove> + '_ {
+        (0..BOARD_COUNT)
+            .filter(move |&board| self.is_playable(board))
+            .flat_map(move |board| {
+                self.boards[board]
+                    .empty_cells()
+                    .map(move |pos| UltimateMove { board, pos })
+            })
+    }
+
+    pub fn make_move(&mut self, what: Mark, m: UltimateMove) -> Result<(), GameError> {
+        if self.is_over() {
+            return Err(GameError::GameOver);
+        }
+        let player = self.side_to_move;
+        if what != player.standard_mark() {
+            return Err(GameError::WrongMark(what));
+        }
+        if m.board >= BOARD_COUNT {
+            return Err(GameError::OutOfBounds);
+        }
+        if !self.is_playable(m.board) {
+            return Err(GameError::InactiveBoard);
+        }
+        self.boards[m.board].set(m.pos, what)?;
+        if get_winner(&self.boards[m.board]) == Some(what) {
+            self.meta.set_at_ind(m.board, what)?;
+        }
+        self.history.push((m, self.active_board));
+        // the cell within its board names the next board
+        let next = m.pos.ind();
+        self.active_board = if self.is_decided(next) {
+            None
+        } else {
+            Some(next)
+        };
+        self.side_to_move = player.opponent();
+        self.result = if get_winner(&self.meta).is_some() {
+            Some(GameResult::Win(player))
+        } else if (0..BOARD_COUNT).all(|board| self.is_decided(board)) {
+            Some(GameResult::Draw)
+        } else {
+            None
+        };
+        Ok(())
+    }
+
+    /// Takes back the last move, returns it.
+    pub fn undo_move(&mut self) -> Option<UltimateMove> {
+        let (m, active_board) = self.history.pop()?;
+        self.boards[m.board].unset(m.pos);
+        if get_winner(&self.boards[m.board]).is_none() {
+            let meta_pos = self.meta.position_of_ind(m.board).ok()?;
+            self.meta.unset(meta_pos);
+        }
+        self.active_board = active_board;
+        self.side_to_move = self.side_to_move.opponent();
+        self.result = None;
+        Some(m)
+    }
+}
+
+/// Renders all boards in one grid. Empty cells of the boards that may be
+/// played on are dotted, those of won boards show the winner in lowercase.
+impl fmt::Display for UltimateGame {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let side = SIDE_LENGTH * SIDE_LENGTH;
+        let separator = format!("\n   {}+", "+-------".repeat(SIDE_LENGTH));
+        write!(formatter, "\n    ")?;
+        for col in 0..side {
+            let gap = if col > 0 && col % SIDE_LENGTH == 0 {
+                "  "
+            } else {
+                ""
+            };
+            write!(formatter, "{} {}", gap, col)?;
+        }
+        for row in 0..side {
+            if row % SIDE_LENGTH == 0 {
+                write!(formatter, "{}", separator)?;
+            }
+            write!(formatter, "\n{:^3}|", row)?;
+            for col in 0..side {
+                let m = self.position(col, row).map_err(|_| fmt::Error)?;
+                let symbol = match (
+                    self.boards[m.board].get(m.pos),
+                    self.meta.get_at_ind(m.board),
+                ) {
+                    (Some(Mark::Cross), _) => 'X',
+                    (Some(Mark::Nought), _) => 'O',
+                    (None, Ok(Some(Mark::Cross))) => 'x',
+                    (None, Ok(Some(Mark::Nought))) => 'o',
+                    _ if self.is_playable(m.board) => '.',
+                    _ => ' ',
+                };
+                write!(formatter, " {}", symbol)?;
+                if col % SIDE_LENGTH == SIDE_LENGTH - 1 {
+                    write!(formatter, " |")?;
+                }
+            }
+        }
+        writeln!(formatter, "{}", separator)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Mark::*;
+
+    fn play(game: &mut UltimateGame, x: usize, y: usize) -> Result<(), GameError> {
+        let m = game.position(x, y)?;
+        game.make_move(game.get_side_to_move().standard_mark(), m)
+    }
+
+    #[test]
+    fn test_cell_picks_next_board() {
+        let mut game = UltimateGame::new();
+        assert_eq!(81, game.legal_moves().count());
+
+        // the top right cell of the center board
+        play(&mut game, 5, 3).unwrap();
+        assert_eq!(Some(2), game.get_active_board());
+        assert_eq!(9, game.legal_moves().count());
+        assert!(game.legal_moves().all(|m| m.board == 2));
+        assert_eq!(Err(GameError::InactiveBoard), play(&mut game, 0, 0));
+    }
+
+    #[test]
+    fn test_won_board_claims_meta_cell() {
+        let mut game = UltimateGame::new();
+        // X takes the top row of board 0, O keeps sending it back there
+        let moves = [(0, 0), (1, 1), (5, 5), (6, 6), (1, 0), (3, 0), (2, 0)];
+        for &(x, y) in &moves {
+            play(&mut game, x, y).unwrap();
+        }
+        assert_eq!(Ok(Some(Cross)), game.get_meta_grid().get_at_ind(0));
+        assert!(game.is_decided(0));
+        assert_eq!(None, game.get_result());
+
+        // sent to the decided board 0, X may pick any other board
+        play(&mut game, 6, 0).unwrap();
+        assert_eq!(None, game.get_active_board());
+        assert_eq!(Err(GameError::InactiveBoard), play(&mut game, 0, 1));
+        play(&mut game, 4, 4).unwrap();
+    }
+
+    fn board_with_crosses(cells: &[usize]) -> Grid {
+        let mut grid = Grid::new(SIDE_LENGTH);
+        for &ind in cells {
+            grid.set_at_ind(ind, Cross).unwrap();
+        }
+        grid
+    }
+
+    #[test]
+    fn test_meta_line_wins() {
+        let mut game = UltimateGame::new();
+        for board in 0..2 {
+            game.boards[board] = board_with_crosses(&[0, 1, 2]);
+            game.meta.set_at_ind(board, Cross).unwrap();
+        }
+        game.boards[2] = board_with_crosses(&[0, 1]);
+
+        let m = game.position(8, 0).unwrap();
+        game.make_move(Cross, m).unwrap();
+        assert_eq!(Some(GameResult::Win(Player::FIRST)), game.get_result());
+        assert_eq!(0, game.legal_moves().count());
+
+        assert_eq!(Some(m), game.undo_move());
+        assert_eq!(None, game.get_result());
+        assert_eq!(Ok(None), game.get_meta_grid().get_at_ind(2));
+    }
+
+    #[test]
+    fn test_render_dots_active_board() {
+        let mut game = UltimateGame::new();
+        play(&mut game, 4, 4).unwrap();
+        let rendered = game.to_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!("     0 1 2   3 4 5   6 7 8", lines[1]);
+        assert_eq!(" 4 |       | . X . |       |", lines[8]);
+    }
+}
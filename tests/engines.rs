@@ -0,0 +1,541 @@
+use std::sync::Arc;
+use ttt::mcts::MonteCarloEngine;
+use ttt::notakto::NotaktoEngine;
+use ttt::notakto::{NotaktoGame, NotaktoMove};
+use ttt::quantum::QuantumGame;
+use ttt::random::Random;
+use ttt::{
+    gomoku, gravity, obstacles, order_chaos, qubic, Cell, PerfectPlayTable, SearchStats,
+    TableEngine,
+};
+use ttt::{
+    AlphaBetaPruning, Engine, Game, GameResult, Grid, Mark, OpeningBook, Player, WinCondition,
+};
+
+fn quiet_engine(side: Player) -> AlphaBetaPruning {
+    let mut engine = AlphaBetaPruning::new(side);
+    engine.set_verbose(false);
+    engine
+}
+
+/// Search engine for boards too large to solve.
+fn limited_engine(side: Player, depth: usize) -> AlphaBetaPruning {
+    let mut engine = quiet_engine(side);
+    engine.set_depth_limit(Some(depth));
+    engine
+}
+
+fn play_out(game: &mut Game, cross: &mut dyn Engine, nought: &mut dyn Engine) -> Vec<SearchStats> {
+    let mut stats = Vec::new();
+    while !game.is_over() {
+        let result = if game.get_side_to_move() == Player::FIRST {
+            cross.try_make_move(game)
+        } else {
+            nought.try_make_move(game)
+        };
+        stats.push(result.unwrap());
+    }
+    stats
+}
+
+#[test]
+fn test_search_against_itself_is_a_draw() {
+    let mut game = Game::new(3);
+    let stats = play_out(
+        &mut game,
+        &mut quiet_engine(Player::FIRST),
+        &mut quiet_engine(Player::SECOND),
+    );
+
+    assert_eq!(9, stats.len());
+    assert_eq!(Some(GameResult::Draw), game.get_result());
+}
+
+#[test]
+fn test_table_against_search_is_a_draw() {
+    let table = Arc::new(PerfectPlayTable::generate(3).unwrap());
+    let mut game = Game::new(3);
+    play_out(
+        &mut game,
+        &mut TableEngine::new(Player::FIRST, table),
+        &mut quiet_engine(Player::SECOND),
+    );
+
+    assert_eq!(Some(GameResult::Draw), game.get_result());
+}
+
+#[test]
+fn test_engine_takes_book_moves() {
+    let mut book = OpeningBook::default();
+    let empty = Grid::new(3);
+    book.insert(&empty, empty.position(2, 2).unwrap());
+    let mut engine = quiet_engine(Player::FIRST);
+    engine.set_opening_book(Arc::new(book));
+
+    let mut game = Game::new(3);
+    let stats = engine.try_make_move(&mut game).unwrap();
+    assert!(stats.from_book);
+    assert_eq!(Ok(Some(Mark::Cross)), game.get_grid().get_at_pos(2, 2));
+}
+
+#[test]
+fn test_engine_exploits_mistake() {
+    let mut g = Grid::new(3);
+    g.set_at_pos(1, 1, Mark::Cross).unwrap();
+    g.set_at_pos(1, 0, Mark::Nought).unwrap();
+    let mut game = Game::from_grid(g).unwrap();
+
+    let mut cross = quiet_engine(Player::FIRST);
+    let mut nought = quiet_engine(Player::SECOND);
+    play_out(&mut game, &mut cross, &mut nought);
+
+    assert_eq!(Some(GameResult::Win(Player::FIRST)), game.get_result());
+}
+
+#[test]
+fn test_game_rejects_moves_out_of_turn() {
+    let mut game = Game::new(3);
+    let pos = game.get_grid().position(0, 0).unwrap();
+    assert_eq!(
+        Err(ttt::GameError::WrongMark(Mark::Nought)),
+        game.make_move(Mark::Nought, pos)
+    );
+    quiet_engine(Player::FIRST)
+        .try_make_move(&mut game)
+        .unwrap();
+    assert_eq!(Player::SECOND, game.get_side_to_move());
+}
+
+fn nodes_to_solve(game: &Game, move_ordering: bool) -> u64 {
+    let mut engine = quiet_engine(game.get_side_to_move());
+    engine.set_move_ordering(move_ordering);
+    engine
+        .try_make_move(&mut game.clone())
+        .unwrap()
+        .nodes_visited
+}
+
+#[test]
+fn test_move_ordering_saves_nodes_on_4x4() {
+    let openings: [&[(usize, usize)]; 3] = [
+        &[(1, 1), (2, 2), (0, 0), (3, 3)],
+        &[(0, 0), (1, 1), (3, 0), (2, 2), (0, 3)],
+        &[(1, 2), (2, 1), (0, 0), (3, 3), (1, 1)],
+    ];
+    for opening in openings.iter() {
+        let mut game = Game::new(4);
+        for &(x, y) in opening.iter() {
+            let pos = game.get_grid().position(x, y).unwrap();
+            game.make_move(game.get_mark_to_move(), pos).unwrap();
+        }
+        let ordered = nodes_to_solve(&game, true);
+        let unordered = nodes_to_solve(&game, false);
+        assert!(
+            ordered < unordered,
+            "{:?}: {} nodes ordered, {} unordered",
+            opening,
+            ordered,
+            unordered
+        );
+    }
+}
+
+fn gomoku_game(crosses: &[(usize, usize)], noughts: &[(usize, usize)]) -> Game {
+    let rules = gomoku::Rules::Freestyle;
+    let mut g =
+        Grid::with_win_condition(gomoku::DEFAULT_SIDE_LENGTH, rules.win_condition()).unwrap();
+    for &(x, y) in crosses {
+        g.set_at_pos(x, y, Mark::Cross).unwrap();
+    }
+    for &(x, y) in noughts {
+        g.set_at_pos(x, y, Mark::Nought).unwrap();
+    }
+    Game::from_grid(g).unwrap()
+}
+
+#[test]
+fn test_gomoku_engine_completes_five() {
+    let mut game = gomoku_game(
+        &[(3, 7), (4, 7), (5, 7), (6, 7)],
+        &[(2, 7), (3, 8), (10, 10), (11, 3)],
+    );
+    limited_engine(Player::FIRST, gomoku::SEARCH_DEPTH)
+        .try_make_move(&mut game)
+        .unwrap();
+    assert_eq!(Some(GameResult::Win(Player::FIRST)), game.get_result());
+}
+
+#[test]
+fn test_gomoku_engine_blocks_four() {
+    let mut game = gomoku_game(
+        &[(3, 7), (4, 7), (5, 7), (6, 7), (8, 2)],
+        &[(2, 7), (3, 8), (10, 10), (11, 3)],
+    );
+    limited_engine(Player::SECOND, gomoku::SEARCH_DEPTH)
+        .try_make_move(&mut game)
+        .unwrap();
+    assert_eq!(Ok(Some(Mark::Nought)), game.get_grid().get_at_pos(7, 7));
+}
+
+#[test]
+fn test_gomoku_engine_extends_two_to_three() {
+    // the noughts are closer to the center, where a flat score would play
+    let mut game = gomoku_game(&[(2, 2), (3, 2)], &[(10, 10), (12, 4)]);
+    limited_engine(Player::FIRST, gomoku::SEARCH_DEPTH)
+        .try_make_move(&mut game)
+        .unwrap();
+    let grid = game.get_grid();
+    assert!([(1, 2), (4, 2)]
+        .iter()
+        .any(|&(x, y)| grid.get_at_pos(x, y) == Ok(Some(Mark::Cross))));
+}
+
+#[test]
+fn test_gomoku_engines_play_a_game() {
+    let mut game = gomoku::new_game(9, gomoku::Rules::Standard).unwrap();
+    // shallow enough to keep the test quick in debug builds
+    let mut cross = limited_engine(Player::FIRST, 2);
+    let mut nought = limited_engine(Player::SECOND, 2);
+    let stats = play_out(&mut game, &mut cross, &mut nought);
+    assert!(game.is_over());
+    assert_eq!(game.get_move_count(), stats.len());
+}
+
+#[test]
+fn test_renju_engine_avoids_forbidden_moves() {
+    let mut game = gomoku::new_game(gomoku::DEFAULT_SIDE_LENGTH, gomoku::Rules::Renju).unwrap();
+    for &(x, y) in &[
+        (5, 4),
+        (0, 0),
+        (6, 4),
+        (14, 0),
+        (7, 2),
+        (0, 14),
+        (7, 3),
+        (14, 14),
+    ] {
+        let pos = game.get_grid().position(x, y).unwrap();
+        game.make_move(game.get_mark_to_move(), pos).unwrap();
+    }
+    let forbidden = game.get_grid().position(7, 4).unwrap();
+    assert!(game.is_forbidden(forbidden));
+
+    limited_engine(Player::FIRST, gomoku::SEARCH_DEPTH)
+        .try_make_move(&mut game)
+        .unwrap();
+    assert_eq!(Ok(None), game.get_grid().get_at_pos(7, 4));
+}
+
+fn connect_four_game(columns: &[usize]) -> Game {
+    let mut game = gravity::new_game(
+        gravity::DEFAULT_WIDTH,
+        gravity::DEFAULT_HEIGHT,
+        gravity::DEFAULT_WIN_LENGTH,
+    )
+    .unwrap();
+    for &column in columns {
+        let pos = game.drop_position(column).unwrap();
+        game.make_move(game.get_mark_to_move(), pos).unwrap();
+    }
+    game
+}
+
+#[test]
+fn test_connect_four_engine_blocks_three() {
+    // crosses on the floor in columns 1 to 3, a nought closes column 4
+    let mut game = connect_four_game(&[1, 4, 2, 2, 3]);
+    limited_engine(Player::SECOND, gravity::SEARCH_DEPTH)
+        .try_make_move(&mut game)
+        .unwrap();
+    let floor = gravity::DEFAULT_HEIGHT - 1;
+    assert_eq!(Ok(Some(Mark::Nought)), game.get_grid().get_at_pos(0, floor));
+}
+
+#[test]
+fn test_connect_four_engines_play_a_game() {
+    let mut game = connect_four_game(&[]);
+    // shallow enough to keep the test quick in debug builds
+    let mut cross = limited_engine(Player::FIRST, 4);
+    let mut nought = limited_engine(Player::SECOND, 4);
+    let stats = play_out(&mut game, &mut cross, &mut nought);
+    assert!(game.is_over());
+    assert_eq!(game.get_move_count(), stats.len());
+}
+
+fn misere_game(side_length: usize) -> Game {
+    let mut game = Game::new(side_length);
+    game.set_misere(true);
+    game
+}
+
+#[test]
+fn test_misere_perfect_play_is_a_draw() {
+    let mut game = misere_game(3);
+    play_out(
+        &mut game,
+        &mut quiet_engine(Player::FIRST),
+        &mut quiet_engine(Player::SECOND),
+    );
+
+    assert_eq!(Some(GameResult::Draw), game.get_result());
+}
+
+#[test]
+fn test_misere_only_center_opening_draws() {
+    for ind in 0..9 {
+        let mut game = misere_game(3);
+        let pos = game.get_grid().position_of_ind(ind).unwrap();
+        game.make_move(Mark::Cross, pos).unwrap();
+        play_out(
+            &mut game,
+            &mut quiet_engine(Player::FIRST),
+            &mut quiet_engine(Player::SECOND),
+        );
+
+        let expected = if ind == 4 {
+            GameResult::Draw
+        } else {
+            GameResult::Win(Player::SECOND)
+        };
+        assert_eq!(Some(expected), game.get_result(), "opening at {}", ind);
+    }
+}
+
+#[test]
+fn test_misere_2x2_is_lost_for_cross() {
+    // any two cells of a 2x2 grid form a line
+    let mut game = misere_game(2);
+    play_out(
+        &mut game,
+        &mut quiet_engine(Player::FIRST),
+        &mut quiet_engine(Player::SECOND),
+    );
+
+    assert_eq!(Some(GameResult::Win(Player::SECOND)), game.get_result());
+    assert_eq!(3, game.get_move_count());
+}
+
+#[test]
+fn test_misere_ignores_opening_book() {
+    let mut book = OpeningBook::default();
+    let mut g = Grid::new(3);
+    g.set_at_pos(1, 1, Mark::Cross).unwrap();
+    g.set_at_pos(0, 0, Mark::Nought).unwrap();
+    book.insert(&g, g.position(1, 0).unwrap());
+    let mut engine = quiet_engine(Player::FIRST);
+    engine.set_opening_book(Arc::new(book));
+
+    let mut game = Game::from_grid(g).unwrap();
+    game.set_misere(true);
+    let stats = engine.try_make_move(&mut game).unwrap();
+    assert!(!stats.from_book);
+}
+
+fn wild_game() -> Game {
+    let mut game = Game::new(3);
+    game.set_wild(true);
+    game
+}
+
+#[test]
+fn test_wild_is_a_first_player_win() {
+    let mut game = wild_game();
+    play_out(
+        &mut game,
+        &mut quiet_engine(Player::FIRST),
+        &mut quiet_engine(Player::SECOND),
+    );
+
+    assert_eq!(Some(GameResult::Win(Player::FIRST)), game.get_result());
+}
+
+#[test]
+fn test_wild_engine_completes_line_of_other_mark() {
+    let mut game = wild_game();
+    for &(what, x, y) in &[
+        (Mark::Nought, 0, 0),
+        (Mark::Cross, 2, 2),
+        (Mark::Nought, 1, 0),
+    ] {
+        let pos = game.get_grid().position(x, y).unwrap();
+        game.make_move(what, pos).unwrap();
+    }
+
+    quiet_engine(Player::SECOND)
+        .try_make_move(&mut game)
+        .unwrap();
+    assert_eq!(Some(GameResult::Win(Player::SECOND)), game.get_result());
+    assert_eq!(Ok(Some(Mark::Nought)), game.get_grid().get_at_pos(2, 0));
+}
+
+fn order_and_chaos_game(moves: &[(Mark, usize, usize)]) -> Game {
+    let mut game = order_chaos::new_game().unwrap();
+    for &(what, x, y) in moves {
+        let pos = game.get_grid().position(x, y).unwrap();
+        game.make_move(what, pos).unwrap();
+    }
+    game
+}
+
+#[test]
+fn test_order_engine_completes_five_noughts() {
+    let mut game = order_and_chaos_game(&[
+        (Mark::Nought, 0, 1),
+        (Mark::Cross, 5, 5),
+        (Mark::Nought, 1, 1),
+        (Mark::Cross, 5, 4),
+        (Mark::Nought, 2, 1),
+        (Mark::Cross, 0, 5),
+        (Mark::Nought, 3, 1),
+        (Mark::Cross, 2, 4),
+    ]);
+    limited_engine(order_chaos::ORDER, order_chaos::SEARCH_DEPTH)
+        .try_make_move(&mut game)
+        .unwrap();
+    assert_eq!(Some(GameResult::Win(order_chaos::ORDER)), game.get_result());
+}
+
+#[test]
+fn test_chaos_engine_spoils_four_with_other_mark() {
+    let mut game = order_and_chaos_game(&[
+        (Mark::Cross, 0, 2),
+        (Mark::Nought, 5, 0),
+        (Mark::Cross, 1, 2),
+        (Mark::Nought, 0, 5),
+        (Mark::Cross, 2, 2),
+        (Mark::Nought, 5, 5),
+        (Mark::Cross, 3, 2),
+    ]);
+    limited_engine(order_chaos::CHAOS, order_chaos::SEARCH_DEPTH)
+        .try_make_move(&mut game)
+        .unwrap();
+    assert_eq!(None, game.get_result());
+    assert_eq!(Ok(Some(Mark::Nought)), game.get_grid().get_at_pos(4, 2));
+}
+
+#[test]
+fn test_qubic_engine_blocks_row_through_layers() {
+    let mut game = qubic::new_game(qubic::DEFAULT_SIDE_LENGTH).unwrap();
+    let moves = [(0, 0, 0), (3, 0, 0), (1, 1, 1), (3, 1, 0), (2, 2, 2)];
+    for (ind, &(x, y, z)) in moves.iter().enumerate() {
+        let side = if ind % 2 == 0 {
+            Mark::Cross
+        } else {
+            Mark::Nought
+        };
+        let pos = game.get_grid().position_3d(x, y, z).unwrap();
+        game.make_move(side, pos).unwrap();
+    }
+
+    let mut engine = quiet_engine(Player::SECOND);
+    engine.set_depth_limit(Some(2));
+    engine.try_make_move(&mut game).unwrap();
+    assert_eq!(Ok(Some(Mark::Nought)), game.get_grid().get_at_ind(63));
+}
+
+#[test]
+fn test_toroidal_3x3_is_a_first_player_win() {
+    let mut grid = Grid::new(3);
+    grid.set_toroidal(true);
+    let mut game = Game::from_grid(grid).unwrap();
+    play_out(
+        &mut game,
+        &mut quiet_engine(Player::FIRST),
+        &mut quiet_engine(Player::SECOND),
+    );
+
+    assert_eq!(Some(GameResult::Win(Player::FIRST)), game.get_result());
+}
+
+#[test]
+fn test_toroidal_engine_blocks_row_across_edge() {
+    let wc = WinCondition {
+        length: 4,
+        overlines: true,
+    };
+    let mut grid = Grid::with_win_condition(6, wc).unwrap();
+    grid.set_toroidal(true);
+    let mut game = Game::from_grid(grid).unwrap();
+    // crosses on both sides of the right edge, only open across it
+    let moves = [
+        (Mark::Cross, 4, 2),
+        (Mark::Nought, 3, 2),
+        (Mark::Cross, 5, 2),
+        (Mark::Nought, 2, 5),
+        (Mark::Cross, 0, 2),
+    ];
+    for &(side, x, y) in &moves {
+        let pos = game.get_grid().position(x, y).unwrap();
+        game.make_move(side, pos).unwrap();
+    }
+
+    let mut engine = quiet_engine(Player::SECOND);
+    engine.set_depth_limit(Some(2));
+    engine.try_make_move(&mut game).unwrap();
+    assert_eq!(Ok(Some(Mark::Nought)), game.get_grid().get_at_pos(1, 2));
+}
+
+#[test]
+fn test_3x3_with_blocked_center_is_a_draw() {
+    let mut grid = Grid::new(3);
+    obstacles::block_cells(&mut grid, &[(1, 1)]).unwrap();
+    let mut game = Game::from_grid(grid).unwrap();
+    let stats = play_out(
+        &mut game,
+        &mut quiet_engine(Player::FIRST),
+        &mut quiet_engine(Player::SECOND),
+    );
+
+    // every square but the blocked one gets played
+    assert_eq!(8, stats.len());
+    assert_eq!(Some(GameResult::Draw), game.get_result());
+    let center = game.get_grid().position(1, 1).unwrap();
+    assert_eq!(Cell::Blocked, game.get_grid().get_cell(center));
+}
+
+#[test]
+fn test_quantum_engine_beats_random_play() {
+    let mut wins = 0;
+    for seed in 0..4 {
+        let mut game = QuantumGame::new();
+        let mut engine = MonteCarloEngine::new(Player::FIRST);
+        engine.set_seed(seed);
+        engine.set_iterations(200);
+        let mut random = Random::new(seed);
+        while !game.is_over() {
+            if game.get_side_to_move() == Player::FIRST {
+                engine.try_make_move(&mut game).unwrap();
+            } else {
+                let moves = game.legal_moves();
+                let m = moves[random.below(moves.len())];
+                game.make_move(Player::SECOND, m).unwrap();
+            }
+        }
+        if game.get_result() == Some(GameResult::Win(Player::FIRST)) {
+            wins += 1;
+        }
+    }
+    assert!(wins >= 3, "{} wins", wins);
+}
+
+#[test]
+fn test_notakto_engine_never_loses_a_won_position() {
+    // the second player wins on two boards, the first on one or three
+    for &(board_count, winner) in &[(1, Player::FIRST), (2, Player::SECOND), (3, Player::FIRST)] {
+        for seed in 0..8 {
+            let mut game = NotaktoGame::new(board_count).unwrap();
+            let mut engine = NotaktoEngine::new(winner);
+            let mut random = Random::new(seed);
+            while !game.is_over() {
+                if game.get_side_to_move() == winner {
+                    engine.try_make_move(&mut game).unwrap();
+                } else {
+                    let moves: Vec<NotaktoMove> = game.legal_moves().collect();
+                    let m = moves[random.below(moves.len())];
+                    game.make_move(winner.opponent(), m).unwrap();
+                }
+            }
+            assert_eq!(Some(GameResult::Win(winner)), game.get_result());
+        }
+    }
+}
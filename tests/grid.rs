@@ -0,0 +1,44 @@
+use ttt::{get_winner, Grid, Mark};
+
+#[test]
+fn test_fill_grid_without_winner() {
+    let mut g = Grid::new(3);
+    let marks = [
+        Mark::Cross,
+        Mark::Nought,
+        Mark::Cross,
+        Mark::Cross,
+        Mark::Nought,
+        Mark::Nought,
+        Mark::Nought,
+        Mark::Cross,
+        Mark::Cross,
+    ];
+    for (ind, mark) in marks.iter().enumerate() {
+        assert_eq!(None, get_winner(&g));
+        g.set_at_ind(ind, *mark).unwrap();
+    }
+    assert!(g.is_full());
+    assert_eq!(None, get_winner(&g));
+}
+
+#[test]
+fn test_winner_on_large_grid() {
+    let mut g = Grid::new(5);
+    for i in 0..5 {
+        assert_eq!(None, get_winner(&g));
+        g.set_at_pos(4 - i, i, Mark::Nought).unwrap();
+    }
+    assert_eq!(Some(Mark::Nought), get_winner(&g));
+}
+
+#[test]
+fn test_display_shows_marks() {
+    let mut g = Grid::new(2);
+    g.set_at_pos(0, 0, Mark::Cross).unwrap();
+    g.set_at_pos(1, 1, Mark::Nought).unwrap();
+
+    let rendered = g.to_string();
+    assert!(rendered.contains(" 0 | X |   |"), "{}", rendered);
+    assert!(rendered.contains(" 1 |   | O |"), "{}", rendered);
+}